@@ -0,0 +1,172 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+pub const IMAGES_DIR: &str = "output-images";
+pub const METADATA_DIR: &str = "output-metadata";
+pub const THUMBNAILS_DIR: &str = "output-thumbnails";
+pub const CONTACT_SHEET: &str = "contact-sheet.png";
+
+/// One NFT in a recovered archive, matched up by edition number
+#[derive(Clone, Debug)]
+pub struct ArchiveEntry {
+    pub edition: u32,
+    pub image: Option<PathBuf>,
+    pub metadata: Option<PathBuf>,
+}
+
+/// Pulls the edition number out of a recovered file name.
+///
+/// Handles the names written by the recovery commands: `12.png`, `12-<coin>.png`
+/// and `metadata_012.json`.
+#[must_use]
+pub fn edition_from_filename(path: &Path) -> Option<u32> {
+    let stem = path.file_stem()?.to_str()?;
+    let stem = stem.strip_prefix("metadata_").unwrap_or(stem);
+    let digits: String = stem.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Whether a filename read from the chain is a single plain file name, so joining it to an output
+/// directory can't write anywhere else. Whoever inscribed the collection chose it, so separators,
+/// `..`, absolute paths and hidden names are all refused
+#[must_use]
+pub fn is_safe_filename(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.starts_with('.')
+        && !name.contains(['/', '\\', '\0'])
+        && matches!(components.next(), Some(Component::Normal(_)))
+        && components.next().is_none()
+}
+
+/// The name an image is written under: its inscribed filename if that's safe, or else `fallback`
+#[must_use]
+pub fn image_filename(inscribed: Option<&str>, fallback: impl FnOnce() -> String) -> String {
+    match inscribed {
+        Some(name) if is_safe_filename(name) => name.to_string(),
+        Some(name) => {
+            let fallback = fallback();
+            warn!(inscribed = ?name, file = %fallback, "Ignoring unsafe inscribed filename");
+            fallback
+        }
+        None => fallback(),
+    }
+}
+
+/// Percent-encodes a file name for use as one segment of a URL path
+#[must_use]
+pub fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Lists the files directly inside `dir`, sorted by name. A missing directory is treated as empty.
+///
+/// # Errors
+///
+/// Will return `Err` if the directory exists but can't be read
+pub fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Loads the images and metadata under `root` (laid out like the recovery commands write them)
+/// and pairs them by edition number.
+///
+/// # Errors
+///
+/// Will return `Err` if the output directories exist but can't be read
+pub fn load_entries(root: &Path) -> Result<Vec<ArchiveEntry>> {
+    let mut entries: Vec<ArchiveEntry> = vec![];
+
+    for metadata in list_files(&root.join(METADATA_DIR))? {
+        if let Some(edition) = edition_from_filename(&metadata) {
+            entry_for(&mut entries, edition).metadata = Some(metadata);
+        }
+    }
+    for image in list_files(&root.join(IMAGES_DIR))? {
+        if let Some(edition) = edition_from_filename(&image) {
+            let entry = entry_for(&mut entries, edition);
+            if entry.image.is_none() {
+                entry.image = Some(image);
+            }
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.edition);
+    Ok(entries)
+}
+
+fn entry_for(entries: &mut Vec<ArchiveEntry>, edition: u32) -> &mut ArchiveEntry {
+    let index = match entries.iter().position(|entry| entry.edition == edition) {
+        Some(index) => index,
+        None => {
+            entries.push(ArchiveEntry {
+                edition,
+                image: None,
+                metadata: None,
+            });
+            entries.len() - 1
+        }
+    };
+    &mut entries[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_names_are_safe() {
+        for name in ["1 god.png", "12.png", "god..png", "Zeus"] {
+            assert!(is_safe_filename(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn names_that_leave_the_directory_are_unsafe() {
+        for name in [
+            "",
+            ".",
+            "..",
+            "../escape.png",
+            "images/12.png",
+            "/etc/passwd",
+            "..\\escape.png",
+            "C:\\escape.png",
+            ".hidden.png",
+            "nul\0.png",
+        ] {
+            assert!(!is_safe_filename(name), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn unsafe_names_fall_back() {
+        assert_eq!(image_filename(Some("12.png"), || "x.png".into()), "12.png");
+        assert_eq!(
+            image_filename(Some("../12.png"), || "x.png".into()),
+            "x.png"
+        );
+        assert_eq!(image_filename(None, || "x.png".into()), "x.png");
+    }
+}
@@ -0,0 +1,323 @@
+//! Reassembles inscribed images from the memos of a data chain, in chain order.
+//!
+//! This is independent of where the memos come from, so images can be rebuilt from a live node
+//! or from an offline dump of the chain.
+
+use crate::chia::coins::{decode_conditions, encode_conditions};
+use crate::chia::memo::{parse_memos_from_conditions, MemoStrategy};
+use crate::chia::metadata::decode_metadata;
+use crate::chia::ChainLink;
+use crate::{filter_png_end, filter_png_start, is_png_end, is_png_start, Framing, MarkerMatch};
+use anyhow::Result;
+use chia::protocol::Bytes;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use serde::{Deserialize, Serialize};
+
+/// An image reassembled from its memos
+pub struct AssembledImage {
+    pub data: Vec<u8>,
+    /// The filename inscribed after the end of the image, if there is one
+    pub filename: Option<String>,
+}
+
+/// Collects the memos of a single image until its end marker
+#[derive(Default)]
+pub struct ImageAssembler {
+    framing: Framing,
+    data: Vec<u8>,
+    started: bool,
+}
+
+impl ImageAssembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strips the collection markers of `framing` out of the image data
+    #[must_use]
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Whether memos of an image have been added, but not its end
+    #[must_use]
+    pub fn in_progress(&self) -> bool {
+        self.started
+    }
+
+    /// Adds the next memo, returning the image once its end marker is reached
+    ///
+    /// # Errors
+    ///
+    /// Fails if the first memo doesn't start an image.
+    pub fn push(&mut self, memo: &Bytes) -> Result<Option<AssembledImage>> {
+        if !self.started && !is_png_start(memo) {
+            anyhow::bail!("Not the start of an image");
+        }
+        self.started = true;
+
+        // Check for the filename before we strip it out of the memo
+        let filename = self.framing.get_filename(memo);
+
+        // Filter known prefixes and suffixes that might be in the data
+        let mut memo = filter_png_start(memo);
+        memo = filter_png_end(&memo);
+        memo = self.framing.filter_collection_start(&memo);
+        memo = self.framing.filter_collection_end(&memo);
+
+        self.data.extend(memo.as_ref());
+
+        if is_png_end(&memo) {
+            self.started = false;
+            return Ok(Some(AssembledImage {
+                data: std::mem::take(&mut self.data),
+                filename,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// Collects the memos of a collection, from its start marker through the image carrying its end marker
+#[derive(Default)]
+pub struct CollectionAssembler {
+    image: ImageAssembler,
+    started: bool,
+    finished: bool,
+}
+
+impl CollectionAssembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks for the collection markers of `framing` instead of the Chia Gods ones
+    #[must_use]
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.image = self.image.with_framing(framing);
+        self
+    }
+
+    /// Whether the next memo starts a new image
+    #[must_use]
+    pub fn at_image_start(&self) -> bool {
+        !self.image.in_progress()
+    }
+
+    /// Whether the image carrying the end of the collection marker has been assembled
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Adds the next memo, returning each image once its end marker is reached
+    ///
+    /// # Errors
+    ///
+    /// Fails if the first memo doesn't start a collection, a memo doesn't start an image where
+    /// one is expected, or memos continue after the end of the collection.
+    pub fn push(&mut self, memo: &Bytes) -> Result<Option<AssembledImage>> {
+        if self.finished {
+            anyhow::bail!("Data continues after the end of the collection");
+        }
+        if !self.started && !self.image.framing.is_collection_start(memo) {
+            anyhow::bail!("Not the start of a collection");
+        }
+        self.started = true;
+
+        let image = self.image.push(memo)?;
+        if image.is_some() && self.image.framing.is_collection_end(memo) {
+            self.finished = true;
+        }
+        Ok(image)
+    }
+}
+
+/// Collects the memos of metadata, which can span several coins, until its end marker
+#[derive(Default)]
+pub struct MetadataAssembler {
+    framing: Framing,
+    data: Vec<u8>,
+}
+
+impl MetadataAssembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks for the metadata markers of `framing` instead of the Chia Gods ones
+    #[must_use]
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Adds the next memo, returning the payload between the metadata markers once the end marker
+    /// is reached, or right away when the first memo holds complete metadata without one
+    ///
+    /// # Errors
+    ///
+    /// Fails if the first memo doesn't start the metadata.
+    pub fn push(&mut self, memo: &Bytes) -> Result<Option<Bytes>> {
+        if self.data.is_empty() && !self.framing.is_meta(memo) {
+            anyhow::bail!("Not a metadata coin");
+        }
+        self.data.extend(memo.as_ref());
+
+        // The end marker can be split between this memo and the previous one
+        let tail_start = self
+            .data
+            .len()
+            .saturating_sub(memo.len() + self.framing.meta_end.len());
+        if !self
+            .framing
+            .is_meta_end(&Bytes::new(self.data[tail_start..].to_vec()))
+        {
+            // Metadata that fits in one memo may have been inscribed without an end marker, so a
+            // first memo that already holds complete metadata is taken on its own
+            let single = self.data.len() == memo.len();
+            if single && decode_metadata(&self.framing.filter_meta_start(memo)).is_ok() {
+                self.data.clear();
+                return Ok(Some(self.framing.filter_meta_start(memo)));
+            }
+            return Ok(None);
+        }
+
+        // Remove start and end meta markers
+        let data = Bytes::new(std::mem::take(&mut self.data));
+        Ok(Some(
+            self.framing
+                .filter_meta_end(&self.framing.filter_meta_start(&data)),
+        ))
+    }
+}
+
+/// One coin of a data chain, as written to a chain dump
+#[derive(Serialize, Deserialize)]
+pub struct ChainRecord {
+    pub coin_id: Bytes32,
+    pub parent_coin_id: Bytes32,
+    pub puzzle_hash: Bytes32,
+    pub amount: u64,
+    pub confirmed_height: u32,
+    pub spent_height: u32,
+    /// The data payload of the spend, hex encoded
+    pub memo: Option<String>,
+    pub markers: Vec<MarkerMatch>,
+    /// Every condition of the spend, as a hex encoded CLVM list, so the payload can be picked
+    /// again with another memo strategy. Missing from dumps written before conditions were kept
+    #[serde(default)]
+    pub conditions: Option<String>,
+}
+
+impl ChainRecord {
+    /// Records a link, noting where the markers of `framing` appear in its memo
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spend's conditions can't be serialized.
+    pub fn from_link(link: &ChainLink, framing: &Framing) -> Result<Self> {
+        Ok(Self {
+            coin_id: link.coin.coin.name(),
+            parent_coin_id: link.coin.coin.parent_coin_info,
+            puzzle_hash: link.coin.coin.puzzle_hash,
+            amount: link.coin.coin.amount,
+            confirmed_height: link.coin.confirmed_block_index,
+            spent_height: link.coin.spent_block_index,
+            memo: link.memo.as_ref().map(hex::encode),
+            markers: link
+                .memo
+                .as_ref()
+                .map(|memo| framing.find_markers(memo))
+                .unwrap_or_default(),
+            conditions: Some(hex::encode(encode_conditions(&link.conditions)?)),
+        })
+    }
+
+    /// The data payload picked by `memo_strategy`. Dumps without conditions can only give the
+    /// payload that was picked when they were written
+    ///
+    /// # Errors
+    ///
+    /// Fails if the conditions or memo aren't valid.
+    pub fn memo_for(&self, memo_strategy: MemoStrategy) -> Result<Option<Bytes>> {
+        match &self.conditions {
+            Some(conditions) => parse_memos_from_conditions(
+                decode_conditions(&hex::decode(conditions)?)?,
+                memo_strategy,
+            ),
+            None => Ok(self
+                .memo
+                .as_ref()
+                .map(hex::decode)
+                .transpose()?
+                .map(Bytes::new)),
+        }
+    }
+
+    /// The data payload picked by `memo_strategy`, which every coin in a data chain must have
+    ///
+    /// # Errors
+    ///
+    /// Fails if the coin has no payload for the memo strategy, or it can't be decoded.
+    pub fn require_memo(&self, memo_strategy: MemoStrategy) -> Result<Bytes> {
+        self.memo_for(memo_strategy)?
+            .ok_or(anyhow::anyhow!("Coin {} has no memo", self.coin_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzipped_metadata() -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder
+            .write_all(br#"[{"name":"One"},{"name":"Two"}]"#)
+            .unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn memo(parts: &[&[u8]]) -> Bytes {
+        Bytes::new(parts.concat())
+    }
+
+    #[test]
+    fn single_memo_without_end_marker_is_complete() {
+        let framing = Framing::CHIA_GODS;
+        let payload = gzipped_metadata();
+        let mut assembler = MetadataAssembler::new();
+        let assembled = assembler
+            .push(&memo(&[&framing.meta_start, &payload]))
+            .unwrap();
+        assert_eq!(assembled.as_deref(), Some(&payload[..]));
+    }
+
+    #[test]
+    fn metadata_split_across_memos_waits_for_end_marker() {
+        let framing = Framing::CHIA_GODS;
+        let payload = gzipped_metadata();
+        let (first, second) = payload.split_at(payload.len() / 2);
+        let mut assembler = MetadataAssembler::new();
+        assert!(assembler
+            .push(&memo(&[&framing.meta_start, first]))
+            .unwrap()
+            .is_none());
+        let assembled = assembler.push(&memo(&[second, &framing.meta_end])).unwrap();
+        assert_eq!(assembled.as_deref(), Some(&payload[..]));
+    }
+
+    #[test]
+    fn rejects_memo_without_metadata_start() {
+        assert!(MetadataAssembler::new()
+            .push(&Bytes::new(gzipped_metadata()))
+            .is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! BLS signatures over recovery manifests, so a published archive can be tied to its publisher and
+//! to the coins it was recovered from.
+
+use crate::manifest::Manifest;
+use crate::merkle::decode_hash;
+use ::chia::bls::{master_to_wallet_hardened, sign, verify, PublicKey, SecretKey, Signature};
+use anyhow::{anyhow, Result};
+use bip39::Mnemonic;
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+pub const ATTESTATION_FILE: &str = "attestation.json";
+
+/// Prefixes every signed message, so an attestation signature can't be mistaken for any other
+const DOMAIN: &str = "recovery-tools attestation v1";
+
+/// A signed statement that the files of a recovery have these hashes, and were recovered from
+/// these coins
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attestation {
+    /// The coins the collection was recovered from, such as its start and metadata coins
+    pub coins: Vec<Bytes32>,
+    /// The hex SHA-256 of every file, by path relative to the output directory
+    pub files: BTreeMap<String, String>,
+    /// The root of the Merkle tree over the files, in hex, as published for inclusion proofs
+    pub merkle_root: String,
+    /// The signer's BLS public key, in hex
+    pub public_key: String,
+    /// The BLS signature over the coins and files, in hex
+    pub signature: String,
+}
+
+impl Attestation {
+    /// Signs `manifest` and `coins` with `key`
+    #[must_use]
+    pub fn sign(manifest: &Manifest, coins: &[Bytes32], key: &SecretKey) -> Self {
+        let signature = sign(key, message(manifest, coins));
+        Self {
+            coins: coins.to_vec(),
+            files: manifest
+                .files
+                .iter()
+                .map(|(path, hash)| (path.clone(), hex::encode(hash)))
+                .collect(),
+            merkle_root: hex::encode(manifest.merkle_root()),
+            public_key: hex::encode(key.public_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    /// The files that were signed
+    ///
+    /// # Errors
+    ///
+    /// Fails if a hash isn't 32 bytes of hex.
+    pub fn manifest(&self) -> Result<Manifest> {
+        let files = self
+            .files
+            .iter()
+            .map(|(path, hash)| {
+                let hash: [u8; 32] = hex::decode(hash)?
+                    .try_into()
+                    .map_err(|_| anyhow!("Hash of {path} isn't 32 bytes"))?;
+                Ok((path.clone(), hash))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Manifest { files })
+    }
+
+    /// # Errors
+    ///
+    /// Fails if the public key isn't a valid BLS public key in hex.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        let bytes: [u8; 48] = hex::decode(&self.public_key)?
+            .try_into()
+            .map_err(|_| anyhow!("Public key isn't 48 bytes"))?;
+        Ok(PublicKey::from_bytes(&bytes)?)
+    }
+
+    /// Whether the signature is the public key's signature over the coins and files, and the
+    /// Merkle root is the root of the files. The signed message holds the root, so it's covered by
+    /// the signature too
+    ///
+    /// # Errors
+    ///
+    /// Fails if the public key, signature or a hash can't be decoded.
+    pub fn verify(&self) -> Result<bool> {
+        let bytes: [u8; 96] = hex::decode(&self.signature)?
+            .try_into()
+            .map_err(|_| anyhow!("Signature isn't 96 bytes"))?;
+        let signature = Signature::from_bytes(&bytes)?;
+        let manifest = self.manifest()?;
+        if decode_hash(&self.merkle_root)? != manifest.merkle_root() {
+            return Ok(false);
+        }
+        let message = message(&manifest, &self.coins);
+        Ok(verify(&signature, &self.public_key()?, message))
+    }
+}
+
+/// The bytes that are signed: the domain, then a line per coin, then the manifest in `sha256sum`
+/// format
+fn message(manifest: &Manifest, coins: &[Bytes32]) -> Vec<u8> {
+    let mut message = format!("{DOMAIN}\n");
+    for coin in coins {
+        let _ = writeln!(message, "coin {}", hex::encode(coin.as_slice()));
+    }
+    message.push_str(&manifest.to_sha256sum());
+    message.into_bytes()
+}
+
+/// The key attestations are signed with for a mnemonic: the first hardened wallet key,
+/// `m/12381/8444/2/0`, so the master key is never used directly
+///
+/// # Errors
+///
+/// Fails if the mnemonic isn't a valid BIP-39 phrase.
+pub fn key_from_mnemonic(phrase: &str) -> Result<SecretKey> {
+    let mnemonic = Mnemonic::parse(phrase.trim())?;
+    let master = SecretKey::from_seed(&mnemonic.to_seed(""));
+    Ok(master_to_wallet_hardened(&master, 0))
+}
+
+/// Reads a secret key from 32 bytes of hex
+///
+/// # Errors
+///
+/// Fails if the text isn't 32 bytes of hex, or isn't a valid secret key.
+pub fn key_from_hex(text: &str) -> Result<SecretKey> {
+    let text = text.trim();
+    let bytes: [u8; 32] = hex::decode(text.strip_prefix("0x").unwrap_or(text))?
+        .try_into()
+        .map_err(|_| anyhow!("Secret key isn't 32 bytes"))?;
+    Ok(SecretKey::from_bytes(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256;
+
+    fn key(byte: u8) -> SecretKey {
+        key_from_hex(&hex::encode([byte; 32])).unwrap()
+    }
+
+    fn attestation() -> Attestation {
+        let manifest = Manifest {
+            files: [
+                ("output-images/1.png", "first image"),
+                ("output-images/2.png", "second image"),
+                ("output-metadata/1.json", "{}"),
+            ]
+            .into_iter()
+            .map(|(path, data)| (path.to_string(), sha256(data.as_bytes())))
+            .collect(),
+        };
+        let coins = [Bytes32::from_sized_bytes([7; 32])];
+        Attestation::sign(&manifest, &coins, &key(1))
+    }
+
+    #[test]
+    fn round_trip() {
+        let attestation = attestation();
+        assert!(attestation.verify().unwrap());
+
+        let json = serde_json::to_string(&attestation).unwrap();
+        let read: Attestation = serde_json::from_str(&json).unwrap();
+        assert!(read.verify().unwrap());
+        assert_eq!(
+            read.manifest().unwrap().merkle_root(),
+            decode_hash(&read.merkle_root).unwrap()
+        );
+    }
+
+    #[test]
+    fn mnemonic_key_round_trip() {
+        let phrase = Mnemonic::from_entropy(&[3; 32]).unwrap().to_string();
+        let key = key_from_mnemonic(&phrase).unwrap();
+        assert_eq!(
+            key.to_bytes(),
+            key_from_mnemonic(&format!(" {phrase}\n"))
+                .unwrap()
+                .to_bytes()
+        );
+        let attestation = Attestation::sign(&attestation().manifest().unwrap(), &[], &key);
+        assert!(attestation.verify().unwrap());
+        assert!(key_from_mnemonic("not a mnemonic").is_err());
+    }
+
+    #[test]
+    fn tampered_file_hash() {
+        let mut attestation = attestation();
+        let hash = attestation.files.get_mut("output-images/2.png").unwrap();
+        *hash = hex::encode(sha256(b"replaced image"));
+        assert!(!attestation.verify().unwrap());
+
+        // Even with the Merkle root updated to match, the signature no longer does
+        attestation.merkle_root = hex::encode(attestation.manifest().unwrap().merkle_root());
+        assert!(!attestation.verify().unwrap());
+    }
+
+    #[test]
+    fn tampered_merkle_root() {
+        let mut attestation = attestation();
+        attestation.merkle_root = hex::encode(sha256(b"another root"));
+        assert!(!attestation.verify().unwrap());
+    }
+
+    #[test]
+    fn tampered_public_key() {
+        let mut attestation = attestation();
+        attestation.public_key = hex::encode(key(2).public_key().to_bytes());
+        assert!(!attestation.verify().unwrap());
+
+        attestation.public_key = hex::encode([0xab; 48]);
+        assert!(attestation.verify().is_err());
+    }
+
+    #[test]
+    fn tampered_coins() {
+        let mut attestation = attestation();
+        attestation.coins.push(Bytes32::from_sized_bytes([8; 32]));
+        assert!(!attestation.verify().unwrap());
+    }
+}
@@ -1,4 +1,35 @@
+//! Walks coin chains through a Chia full node to read back inscribed data.
+//!
+//! [`Recoverer`] is the entry point for embedding recovery in other tools:
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use recovery_tools::chia::client::{get_chia_client, NodeConfig};
+//! use recovery_tools::chia::memo::MemoStrategy;
+//! use recovery_tools::chia::Recoverer;
+//! use recovery_tools::coin_id_from_string;
+//! use std::pin::pin;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let recoverer = Recoverer::new(get_chia_client(&NodeConfig::default())?, MemoStrategy::FirstMemo);
+//! let start_coin = coin_id_from_string("8c0793fece985be90444fa6f01f40861047b3b2307053f378ec72f5a5c4bb4d7")?;
+//! let mut images = pin!(recoverer.recover_collection(&start_coin));
+//! while let Some(image) = images.next().await {
+//!     let image = image?;
+//!     println!("#{} {:?}: {} bytes", image.index, image.filename, image.data.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cache;
 pub mod client;
+pub mod clvm;
 pub mod coins;
-pub mod image;
+pub mod locate;
 pub mod memo;
+pub mod metadata;
+pub mod nft;
+mod recoverer;
+
+pub use recoverer::{ChainLink, RecoveredImage, Recoverer, RecoveryObserver};
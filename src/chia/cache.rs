@@ -0,0 +1,111 @@
+//! An on-disk cache of spent coins read from the node.
+//!
+//! Once a coin is spent its record and spend never change, so walking the same chain again, or
+//! recovering a collection that was partly recovered before, reads them from disk instead of the
+//! node. Only spent coins are cached. The cache can be deleted at any time.
+
+use dg_xch_core::blockchain::coin_record::CoinRecord;
+use dg_xch_core::blockchain::coin_spend::CoinSpend;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, warn};
+
+/// A spent coin's record, and its spend once that has been read
+#[derive(Serialize, Deserialize)]
+struct CachedCoin {
+    record: CoinRecord,
+    spend: Option<CoinSpend>,
+}
+
+pub struct SpendCache {
+    dir: PathBuf,
+}
+
+impl SpendCache {
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The record of a coin that was cached once it was spent
+    pub async fn coin_record(&self, coin_id: &Bytes32) -> Option<CoinRecord> {
+        self.read(coin_id).await.map(|cached| cached.record)
+    }
+
+    /// The spend of a cached coin, if it has been read
+    pub async fn coin_spend(&self, coin_id: &Bytes32) -> Option<CoinSpend> {
+        self.read(coin_id).await.and_then(|cached| cached.spend)
+    }
+
+    /// Keeps a coin's record, if the coin is spent
+    pub async fn store_coin_record(&self, record: &CoinRecord) {
+        if record.spent_block_index == 0 {
+            return;
+        }
+        let coin_id = record.coin.name();
+        if self.read(&coin_id).await.is_some() {
+            return;
+        }
+        self.write(
+            &coin_id,
+            &CachedCoin {
+                record: record.clone(),
+                spend: None,
+            },
+        )
+        .await;
+    }
+
+    /// Keeps the spend of a spent coin along with its record
+    pub async fn store_coin_spend(&self, record: &CoinRecord, spend: &CoinSpend) {
+        if record.spent_block_index == 0 {
+            return;
+        }
+        self.write(
+            &record.coin.name(),
+            &CachedCoin {
+                record: record.clone(),
+                spend: Some(spend.clone()),
+            },
+        )
+        .await;
+    }
+
+    fn path(&self, coin_id: &Bytes32) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(coin_id)))
+    }
+
+    async fn read(&self, coin_id: &Bytes32) -> Option<CachedCoin> {
+        read_json(&self.path(coin_id)).await
+    }
+
+    /// Writes through a temporary file, so an interrupted write never leaves a partial entry.
+    /// Failures are only logged, since the node can always be asked again
+    async fn write(&self, coin_id: &Bytes32, cached: &CachedCoin) {
+        let path = self.path(coin_id);
+        let temp = path.with_extension("tmp");
+        let written = async {
+            fs::create_dir_all(&self.dir).await?;
+            fs::write(&temp, serde_json::to_vec(cached)?).await?;
+            fs::rename(&temp, &path).await?;
+            anyhow::Ok(())
+        };
+        if let Err(err) = written.await {
+            warn!(file = %path.display(), error = %err, "Unable to write to the cache");
+        }
+    }
+}
+
+async fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = fs::read(path).await.ok()?;
+    match serde_json::from_slice(&data) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            debug!(file = %path.display(), error = %err, "Ignoring unreadable cache entry");
+            None
+        }
+    }
+}
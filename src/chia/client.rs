@@ -1,40 +1,84 @@
+use anyhow::{anyhow, Result};
 use dg_xch_clients::rpc::full_node::FullnodeClient;
 use dg_xch_clients::ClientSSLConfig;
+use serde::Deserialize;
 use std::env;
-use std::path::Path;
+use std::path::PathBuf;
 
-pub fn get_chia_client(port: u16) -> FullnodeClient {
-    let chia_root = env::var("CHIA_ROOT").unwrap_or_else(|_| {
-        let home_dir = dirs_next::home_dir().unwrap();
-        format!("{}/.chia/mainnet", home_dir.to_str().unwrap())
-    });
-    let chia_root_path = Path::new(&chia_root);
+/// How to reach the full node RPC
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeConfig {
+    pub host: String,
+    pub port: u16,
+    /// The Chia root holding the node's SSL certificates. Defaults to `CHIA_ROOT`, then
+    /// `~/.chia/mainnet`, which are only looked up when a client is made
+    pub chia_root: Option<PathBuf>,
+    /// Request timeout, in seconds
+    pub timeout: u64,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            host: String::from("localhost"),
+            port: 8555,
+            chia_root: None,
+            timeout: 60,
+        }
+    }
+}
+
+impl NodeConfig {
+    /// The configured Chia root, or else `CHIA_ROOT`, or else `~/.chia/mainnet`
+    ///
+    /// # Errors
+    ///
+    /// Fails if none is configured and the home directory can't be found.
+    pub fn chia_root(&self) -> Result<PathBuf> {
+        if let Some(chia_root) = &self.chia_root {
+            return Ok(chia_root.clone());
+        }
+        if let Ok(chia_root) = env::var("CHIA_ROOT") {
+            return Ok(PathBuf::from(chia_root));
+        }
+        let home_dir = dirs_next::home_dir().ok_or(anyhow!(
+            "Unable to find the home directory for the Chia root, set CHIA_ROOT or node.chia_root"
+        ))?;
+        Ok(home_dir.join(".chia/mainnet"))
+    }
+}
+
+/// Makes a client for the node's RPC, authenticated with the certificates in its Chia root
+///
+/// # Errors
+///
+/// Fails if the Chia root or its certificates can't be found, or its path isn't UTF-8.
+pub fn get_chia_client(node: &NodeConfig) -> Result<FullnodeClient> {
+    let chia_root_path = node.chia_root()?;
+    let cert_path = |path: &str| -> Result<String> {
+        let path = chia_root_path.join(path);
+        if !path.is_file() {
+            anyhow::bail!(
+                "Node certificate {} not found, set the Chia root with CHIA_ROOT or node.chia_root",
+                path.display()
+            );
+        }
+        path.to_str()
+            .map(String::from)
+            .ok_or(anyhow!("Path {} isn't UTF-8", path.display()))
+    };
 
     let none_var = None;
-    FullnodeClient::new(
-        "localhost",
-        port,
-        60,
+    Ok(FullnodeClient::new(
+        &node.host,
+        node.port,
+        node.timeout,
         Some(ClientSSLConfig {
-            ssl_crt_path: String::from(
-                chia_root_path
-                    .join("config/ssl/full_node/private_full_node.crt")
-                    .to_str()
-                    .unwrap(),
-            ),
-            ssl_key_path: String::from(
-                chia_root_path
-                    .join("config/ssl/full_node/private_full_node.key")
-                    .to_str()
-                    .unwrap(),
-            ),
-            ssl_ca_crt_path: String::from(
-                chia_root_path
-                    .join("config/ssl/ca/private_ca.crt")
-                    .to_str()
-                    .unwrap(),
-            ),
+            ssl_crt_path: cert_path("config/ssl/full_node/private_full_node.crt")?,
+            ssl_key_path: cert_path("config/ssl/full_node/private_full_node.key")?,
+            ssl_ca_crt_path: cert_path("config/ssl/ca/private_ca.crt")?,
         }),
         &none_var,
-    )
+    ))
 }
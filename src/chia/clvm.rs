@@ -0,0 +1,192 @@
+//! Renders CLVM in the same notation as `opd` from clvm_tools, for inspecting spends by hand.
+
+use anyhow::Result;
+use chia::clvm_traits::ToClvm;
+use chia::protocol::Program;
+use clvmr::allocator::{NodePtr, SExp};
+use clvmr::Allocator;
+
+/// Disassembles a serialized program
+///
+/// # Errors
+///
+/// Fails if the program can't be deserialized.
+pub fn disassemble_program(program: &Program) -> Result<String> {
+    let mut allocator = Allocator::new();
+    let node = program.to_clvm(&mut allocator)?;
+    Ok(disassemble(&allocator, node))
+}
+
+/// Disassembles code, showing operators by name
+#[must_use]
+pub fn disassemble(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, true, &mut output);
+    output
+}
+
+/// Disassembles data, such as condition arguments, where no atom is an operator
+#[must_use]
+pub fn disassemble_data(allocator: &Allocator, node: NodePtr) -> String {
+    let mut output = String::new();
+    write_node(allocator, node, false, &mut output);
+    output
+}
+
+fn write_node(allocator: &Allocator, node: NodePtr, is_code: bool, output: &mut String) {
+    match allocator.sexp(node) {
+        SExp::Atom => output.push_str(&atom_text(allocator.atom(node).as_ref())),
+        SExp::Pair(first, mut rest) => {
+            output.push('(');
+            let operator = match allocator.sexp(first) {
+                SExp::Atom if is_code => operator_name(allocator.atom(first).as_ref()),
+                _ => None,
+            };
+            match operator {
+                Some(name) => output.push_str(name),
+                None => write_node(allocator, first, is_code, output),
+            }
+            // Everything after a quote is data, not code
+            let is_code = is_code && operator != Some("q");
+
+            loop {
+                match allocator.sexp(rest) {
+                    SExp::Pair(item, next) => {
+                        output.push(' ');
+                        write_node(allocator, item, is_code, output);
+                        rest = next;
+                    }
+                    SExp::Atom if allocator.atom(rest).as_ref().is_empty() => break,
+                    SExp::Atom => {
+                        output.push_str(" . ");
+                        write_node(allocator, rest, is_code, output);
+                        break;
+                    }
+                }
+            }
+            output.push(')');
+        }
+    }
+}
+
+/// Operators are shown by name when they start a list
+fn operator_name(atom: &[u8]) -> Option<&'static str> {
+    let [opcode] = atom else {
+        return None;
+    };
+    Some(match opcode {
+        1 => "q",
+        2 => "a",
+        3 => "i",
+        4 => "c",
+        5 => "f",
+        6 => "r",
+        7 => "l",
+        8 => "x",
+        9 => "=",
+        10 => ">s",
+        11 => "sha256",
+        12 => "substr",
+        13 => "strlen",
+        14 => "concat",
+        16 => "+",
+        17 => "-",
+        18 => "*",
+        19 => "/",
+        20 => "divmod",
+        21 => ">",
+        22 => "ash",
+        23 => "lsh",
+        24 => "logand",
+        25 => "logior",
+        26 => "logxor",
+        27 => "lognot",
+        29 => "point_add",
+        30 => "pubkey_for_exp",
+        32 => "not",
+        33 => "any",
+        34 => "all",
+        36 => "softfork",
+        _ => return None,
+    })
+}
+
+/// Atoms are shown as numbers when short, as strings when printable, and otherwise as hex
+fn atom_text(atom: &[u8]) -> String {
+    if atom.is_empty() {
+        return String::from("()");
+    }
+    if atom.len() <= 2 && is_canonical_int(atom) {
+        return int_value(atom).to_string();
+    }
+    if atom.len() > 1
+        && atom
+            .iter()
+            .all(|byte| (0x20..0x7f).contains(byte) && *byte != b'"')
+    {
+        return format!("\"{}\"", String::from_utf8_lossy(atom));
+    }
+    if atom.len() <= 4 && is_canonical_int(atom) {
+        return int_value(atom).to_string();
+    }
+    format!("0x{}", hex::encode(atom))
+}
+
+/// Whether the atom is the shortest encoding of its number, so it reads back the same
+fn is_canonical_int(atom: &[u8]) -> bool {
+    match atom {
+        [0x00, next, ..] => next & 0x80 != 0,
+        [0xff, next, ..] => next & 0x80 == 0,
+        [0x00] => false,
+        _ => true,
+    }
+}
+
+fn int_value(atom: &[u8]) -> i64 {
+    let sign = if atom[0] & 0x80 != 0 { -1 } else { 0 };
+    atom.iter()
+        .fold(sign, |value, byte| (value << 8) | i64::from(*byte))
+}
+
+/// The name of a condition opcode, as used in chialisp
+#[must_use]
+pub fn condition_name(opcode: u32) -> Option<&'static str> {
+    Some(match opcode {
+        1 => "REMARK",
+        43 => "AGG_SIG_PARENT",
+        44 => "AGG_SIG_PUZZLE",
+        45 => "AGG_SIG_AMOUNT",
+        46 => "AGG_SIG_PUZZLE_AMOUNT",
+        47 => "AGG_SIG_PARENT_AMOUNT",
+        48 => "AGG_SIG_PARENT_PUZZLE",
+        49 => "AGG_SIG_UNSAFE",
+        50 => "AGG_SIG_ME",
+        51 => "CREATE_COIN",
+        52 => "RESERVE_FEE",
+        60 => "CREATE_COIN_ANNOUNCEMENT",
+        61 => "ASSERT_COIN_ANNOUNCEMENT",
+        62 => "CREATE_PUZZLE_ANNOUNCEMENT",
+        63 => "ASSERT_PUZZLE_ANNOUNCEMENT",
+        64 => "ASSERT_CONCURRENT_SPEND",
+        65 => "ASSERT_CONCURRENT_PUZZLE",
+        66 => "SEND_MESSAGE",
+        67 => "RECEIVE_MESSAGE",
+        70 => "ASSERT_MY_COIN_ID",
+        71 => "ASSERT_MY_PARENT_ID",
+        72 => "ASSERT_MY_PUZZLEHASH",
+        73 => "ASSERT_MY_AMOUNT",
+        74 => "ASSERT_MY_BIRTH_SECONDS",
+        75 => "ASSERT_MY_BIRTH_HEIGHT",
+        76 => "ASSERT_EPHEMERAL",
+        80 => "ASSERT_SECONDS_RELATIVE",
+        81 => "ASSERT_SECONDS_ABSOLUTE",
+        82 => "ASSERT_HEIGHT_RELATIVE",
+        83 => "ASSERT_HEIGHT_ABSOLUTE",
+        84 => "ASSERT_BEFORE_SECONDS_RELATIVE",
+        85 => "ASSERT_BEFORE_SECONDS_ABSOLUTE",
+        86 => "ASSERT_BEFORE_HEIGHT_RELATIVE",
+        87 => "ASSERT_BEFORE_HEIGHT_ABSOLUTE",
+        90 => "SOFTFORK",
+        _ => return None,
+    })
+}
@@ -1,17 +1,36 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chia::clvm_traits::{FromClvm, ToClvm};
 use chia::protocol::Program;
 use chia::traits::Streamable;
-use chia_wallet_sdk::{run_puzzle, Condition};
-use clvmr::Allocator;
+use chia_wallet_sdk::Condition;
+use clvmr::reduction::Reduction;
+use clvmr::serde::{node_from_bytes, node_to_bytes};
+use clvmr::{run_program, Allocator, ChiaDialect};
 use dg_xch_clients::api::full_node::FullnodeAPI;
 use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::coin::Coin;
 use dg_xch_core::blockchain::coin_record::CoinRecord;
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
+
+/// The most a single block can spend, which also bounds any one spend
+const MAX_BLOCK_COST: u64 = 11_000_000_000;
+const COST_PER_BYTE: u64 = 12_000;
+const CREATE_COIN_COST: u64 = 1_800_000;
+const AGG_SIG_COST: u64 = 1_200_000;
+
+/// The outcome of running a coin's puzzle with its solution
+pub struct SpendOutput {
+    pub conditions: Vec<Condition<Program>>,
+    /// An estimate of the cost the spend adds to a block: running the puzzle, the conditions it
+    /// creates, and the size of the puzzle reveal and solution. The block generator that carries
+    /// the spend adds more, so the real cost is somewhat higher
+    pub estimated_cost: u64,
+}
 
 pub async fn conditions_for_coin(
     client: &FullnodeClient,
     coin: &CoinRecord,
-) -> Result<Vec<Condition>> {
+) -> Result<Vec<Condition<Program>>> {
     let puzz_solution = client
         .get_puzzle_and_solution(&coin.coin.name(), coin.spent_block_index)
         .await?;
@@ -23,14 +42,231 @@ pub async fn conditions_for_coin(
     conditions_for_puzz_solution(&solution_program, &puzzle_program)
 }
 
+/// Serializes a spend's conditions as a CLVM list, so they can be stored and read back
+///
+/// # Errors
+///
+/// Fails if a condition can't be converted to CLVM.
+pub fn encode_conditions(conditions: &[Condition<Program>]) -> Result<Vec<u8>> {
+    let mut allocator = Allocator::new();
+    let ptr = conditions.to_vec().to_clvm(&mut allocator)?;
+    Ok(node_to_bytes(&allocator, ptr)?)
+}
+
+/// Reads back conditions serialized by [`encode_conditions`]
+///
+/// # Errors
+///
+/// Fails if the bytes aren't a serialized CLVM list of conditions.
+pub fn decode_conditions(bytes: &[u8]) -> Result<Vec<Condition<Program>>> {
+    let mut allocator = Allocator::new();
+    let ptr = node_from_bytes(&mut allocator, bytes)?;
+    Ok(Vec::<Condition<Program>>::from_clvm(&allocator, ptr)?)
+}
+
 pub fn conditions_for_puzz_solution(
     solution_program: &Program,
     reveal_program: &Program,
-) -> Result<Vec<Condition>> {
+) -> Result<Vec<Condition<Program>>> {
+    Ok(run_spend(solution_program, reveal_program)?.conditions)
+}
+
+/// Runs a puzzle with its solution, returning the conditions it creates and an estimate of the
+/// cost of the spend
+///
+/// # Errors
+///
+/// Fails if the puzzle raises or exceeds the block cost, or its output isn't a list of conditions.
+pub fn run_spend(solution_program: &Program, reveal_program: &Program) -> Result<SpendOutput> {
     let mut allocator = Allocator::new();
     let puzzle = reveal_program.to_clvm(&mut allocator)?;
     let solution = solution_program.to_clvm(&mut allocator)?;
-    let output = run_puzzle(&mut allocator, puzzle, solution)?;
-    let conditions = Vec::<Condition>::from_clvm(&allocator, output)?;
-    Ok(conditions)
+    let Reduction(execution_cost, output) = run_program(
+        &mut allocator,
+        &ChiaDialect::new(0),
+        puzzle,
+        solution,
+        MAX_BLOCK_COST,
+    )?;
+    let conditions = Vec::<Condition<Program>>::from_clvm(&allocator, output)?;
+
+    let condition_cost: u64 = conditions
+        .iter()
+        .map(|condition| match condition {
+            Condition::CreateCoin(_) => CREATE_COIN_COST,
+            Condition::AggSigParent(_)
+            | Condition::AggSigPuzzle(_)
+            | Condition::AggSigAmount(_)
+            | Condition::AggSigPuzzleAmount(_)
+            | Condition::AggSigParentAmount(_)
+            | Condition::AggSigParentPuzzle(_)
+            | Condition::AggSigUnsafe(_)
+            | Condition::AggSigMe(_) => AGG_SIG_COST,
+            _ => 0,
+        })
+        .sum();
+    let size_cost = (reveal_program.len() + solution_program.len()) as u64 * COST_PER_BYTE;
+
+    Ok(SpendOutput {
+        conditions,
+        estimated_cost: execution_cost + condition_cost + size_cost,
+    })
+}
+
+/// The mojos a spend doesn't pass on to the coins it creates: the coin's amount less theirs.
+///
+/// This isn't the fee paid for the spend. Fees are paid by a whole spend bundle, so another coin in
+/// the bundle may pay the fee for this spend, or this spend may release mojos that another coin in
+/// the bundle takes
+#[must_use]
+pub fn released_amount(coin: &Coin, conditions: &[Condition<Program>]) -> u64 {
+    let created: u64 = conditions
+        .iter()
+        .map(|condition| match condition {
+            Condition::CreateCoin(create_coin) => create_coin.amount,
+            _ => 0,
+        })
+        .sum();
+    coin.amount.saturating_sub(created)
+}
+
+pub fn to_chia_coin(coin: &Coin) -> chia::protocol::Coin {
+    chia::protocol::Coin::new(
+        chia::protocol::Bytes32::new(*coin.parent_coin_info.to_sized_bytes()),
+        chia::protocol::Bytes32::new(*coin.puzzle_hash.to_sized_bytes()),
+        coin.amount,
+    )
+}
+
+/// Picks the coin that continues a data chain out of the coins created by a spend.
+///
+/// A lone child is always followed. When a spend creates several coins, the child that keeps
+/// both the parent's puzzle hash and amount is preferred, then one that keeps the puzzle hash,
+/// then one that keeps the amount. If none of these single out one child, the chain branches
+/// ambiguously and an error listing the candidates is returned.
+pub fn next_coin_in_chain(parent: &Coin, conditions: &[Condition<Program>]) -> Result<Coin> {
+    let mut children: Vec<Coin> = conditions
+        .iter()
+        .filter_map(|condition| match condition {
+            Condition::CreateCoin(create_coin) => Some(Coin {
+                parent_coin_info: parent.coin_id(),
+                puzzle_hash: Bytes32::new(create_coin.puzzle_hash.as_ref()),
+                amount: create_coin.amount,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    if children.len() == 1 {
+        return children.pop().ok_or(anyhow!("Missing child coin"));
+    }
+
+    let rules: [fn(&Coin, &Coin) -> bool; 3] = [
+        |parent, child| child.puzzle_hash == parent.puzzle_hash && child.amount == parent.amount,
+        |parent, child| child.puzzle_hash == parent.puzzle_hash,
+        |parent, child| child.amount == parent.amount,
+    ];
+    for rule in rules {
+        let mut matching: Vec<&Coin> = children
+            .iter()
+            .filter(|child| rule(parent, child))
+            .collect();
+        if matching.len() == 1 {
+            return matching.pop().cloned().ok_or(anyhow!("Missing child coin"));
+        }
+    }
+
+    if children.is_empty() {
+        anyhow::bail!(
+            "Coin {} was spent without creating any child coins",
+            parent.name()
+        );
+    }
+
+    let candidates: Vec<String> = children
+        .iter()
+        .map(|child| {
+            format!(
+                "{} ({} mojos to {})",
+                child.name(),
+                child.amount,
+                child.puzzle_hash
+            )
+        })
+        .collect();
+    anyhow::bail!(
+        "Chain branches ambiguously at coin {}, any of these children could continue it: {}",
+        parent.name(),
+        candidates.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chia_wallet_sdk::CreateCoin;
+
+    const CHAIN_PUZZLE: [u8; 32] = [1; 32];
+    const OTHER_PUZZLE: [u8; 32] = [2; 32];
+
+    fn parent() -> Coin {
+        Coin {
+            parent_coin_info: Bytes32::new(&[9; 32]),
+            puzzle_hash: Bytes32::new(&CHAIN_PUZZLE),
+            amount: 1,
+        }
+    }
+
+    fn create_coin(puzzle_hash: [u8; 32], amount: u64) -> Condition<Program> {
+        Condition::CreateCoin(CreateCoin::new(puzzle_hash.into(), amount, vec![]))
+    }
+
+    fn followed(conditions: &[Condition<Program>]) -> (Bytes32, u64) {
+        let child = next_coin_in_chain(&parent(), conditions).unwrap();
+        assert_eq!(child.parent_coin_info, parent().coin_id());
+        (child.puzzle_hash, child.amount)
+    }
+
+    #[test]
+    fn follows_a_lone_child() {
+        assert_eq!(
+            followed(&[create_coin(OTHER_PUZZLE, 5)]),
+            (Bytes32::new(&OTHER_PUZZLE), 5)
+        );
+    }
+
+    #[test]
+    fn prefers_the_same_puzzle_hash_and_amount() {
+        let conditions = [
+            create_coin(CHAIN_PUZZLE, 5),
+            create_coin(OTHER_PUZZLE, 1),
+            create_coin(CHAIN_PUZZLE, 1),
+        ];
+        assert_eq!(followed(&conditions), (Bytes32::new(&CHAIN_PUZZLE), 1));
+    }
+
+    #[test]
+    fn prefers_the_same_puzzle_hash_over_the_same_amount() {
+        let conditions = [create_coin(OTHER_PUZZLE, 1), create_coin(CHAIN_PUZZLE, 5)];
+        assert_eq!(followed(&conditions), (Bytes32::new(&CHAIN_PUZZLE), 5));
+    }
+
+    #[test]
+    fn falls_back_to_the_same_amount() {
+        let conditions = [create_coin(OTHER_PUZZLE, 5), create_coin([3; 32], 1)];
+        assert_eq!(followed(&conditions), (Bytes32::new(&[3; 32]), 1));
+    }
+
+    #[test]
+    fn fails_without_children() {
+        let err = next_coin_in_chain(&parent(), &[]).unwrap_err();
+        assert!(err.to_string().contains("without creating any child coins"));
+    }
+
+    #[test]
+    fn fails_when_the_chain_branches_ambiguously() {
+        let conditions = [create_coin(CHAIN_PUZZLE, 5), create_coin(CHAIN_PUZZLE, 7)];
+        let err = next_coin_in_chain(&parent(), &conditions).unwrap_err();
+        assert!(err.to_string().contains("branches ambiguously"));
+    }
 }
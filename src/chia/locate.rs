@@ -0,0 +1,199 @@
+use crate::chia::coins::conditions_for_coin;
+use crate::chia::memo::{parse_memos_from_conditions, MemoStrategy};
+use crate::chia::RecoveryObserver;
+use crate::{is_png_start, Framing};
+use anyhow::{anyhow, Result};
+use chia::protocol::Bytes;
+use chia::sha2::Sha256;
+use chia_wallet_sdk::Condition;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::coin_record::CoinRecord;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use tracing::debug;
+
+/// The key coins of a collection, found by walking back from one of its NFTs
+pub struct LocatedData {
+    /// The parent of the NFT that leads directly back to the collection data
+    pub nft_parent: Bytes32,
+    /// The spend between the metadata and the mint whose memo isn't a hint
+    pub gap: Option<GapMemo>,
+    pub metadata_coin: Option<Bytes32>,
+    pub collection_end: Option<Bytes32>,
+    /// Coins that start an image, from the last image in the collection to the first
+    pub image_coins: Vec<Bytes32>,
+    pub collection_start: Option<Bytes32>,
+}
+
+pub struct GapMemo {
+    pub coin_id: Bytes32,
+    pub memo: Bytes,
+}
+
+/// Walks back from the NFT's launcher to the coins holding the collection's metadata and images,
+/// recognizing them by the markers of `framing`. The gap is reported to `observer` as soon as it
+/// is found, so it is kept even if the rest of the walk fails
+pub async fn locate(
+    client: &FullnodeClient,
+    launcher_id: &Bytes32,
+    memo_strategy: MemoStrategy,
+    framing: &Framing,
+    observer: &dyn RecoveryObserver,
+) -> Result<LocatedData> {
+    let mut current_coin = get_nft_parent_in_direct_chain(client, launcher_id).await?;
+    let mut located = LocatedData {
+        nft_parent: current_coin.coin.name(),
+        gap: None,
+        metadata_coin: None,
+        collection_end: None,
+        image_coins: vec![],
+        collection_start: None,
+    };
+
+    while current_coin.spent_block_index > 0 {
+        // First, we don't care about ephemeral coins
+        if current_coin.spent_block_index == current_coin.confirmed_block_index {
+            current_coin = advance_parent(client, &current_coin).await?;
+            continue;
+        }
+        // Keep going until we find the first non-hint memo
+        // First memo is a gap between meta and minting
+        // Then meta coin
+        // Then the "End Collection" image
+        // <image data in between>
+        // Then the "Start Collection" image
+
+        let conditions = conditions_for_coin(client, &current_coin).await?;
+
+        if located.gap.is_none() {
+            // Hints are always the first memo of a CREATE_COIN, whatever strategy the data uses
+            if let Some(first_memo) =
+                parse_memos_from_conditions(conditions.clone(), MemoStrategy::FirstMemo)?
+            {
+                if first_memo.len() != 32 {
+                    let memo = parse_memos_from_conditions(conditions, memo_strategy)?
+                        .unwrap_or(first_memo);
+                    let gap = GapMemo {
+                        coin_id: current_coin.coin.name(),
+                        memo,
+                    };
+                    observer.gap_found(&gap);
+                    located.gap = Some(gap);
+                }
+            }
+            current_coin = advance_parent(client, &current_coin).await?;
+            continue;
+        }
+
+        let Some(memo) = parse_memos_from_conditions(conditions, memo_strategy)? else {
+            // Once the gap is found, every spend up to the start of the collection carries data
+            anyhow::bail!("Unexpected spend with no memo in the collection data")
+        };
+
+        if located.metadata_coin.is_none() {
+            if !framing.is_meta(&memo) {
+                debug!(%memo, "Unexpected memo");
+                anyhow::bail!("Did not find the metadata at the expected location");
+            }
+            located.metadata_coin = Some(current_coin.coin.name());
+            current_coin = advance_parent(client, &current_coin).await?;
+            continue;
+        }
+        // Was checked for none earlier
+        if located.collection_end.is_none() {
+            if !framing.is_collection_end(&memo) {
+                anyhow::bail!("Did not find the end of the collection at the expected location");
+            }
+            located.collection_end = Some(current_coin.coin.name());
+            current_coin = advance_parent(client, &current_coin).await?;
+            continue;
+        }
+
+        if is_png_start(&memo) {
+            located.image_coins.push(current_coin.coin.name());
+        }
+
+        if framing.is_collection_start(&memo) {
+            located.collection_start = Some(current_coin.coin.name());
+            return Ok(located);
+        }
+
+        current_coin = advance_parent(client, &current_coin).await?;
+    }
+
+    Ok(located)
+}
+
+/// Finds the parent coin of the NFT that is in the direct lineage back to the metadata/image coins
+/// Once this coin is found, all that needs to happen is looking at parent_coin_id all the way up
+async fn get_nft_parent_in_direct_chain(
+    client: &FullnodeClient,
+    launcher_id: &Bytes32,
+) -> Result<CoinRecord> {
+    let launcher_coin = client
+        .get_coin_record_by_name(launcher_id)
+        .await?
+        .ok_or(anyhow!("Launcher Coin Record not found."))?;
+
+    let eph_coin = client
+        .get_coin_record_by_name(&launcher_coin.coin.parent_coin_info)
+        .await?
+        .ok_or(anyhow!("Ephemeral Coin Record not found."))?;
+
+    let direct_parent = client
+        .get_coin_record_by_name(&eph_coin.coin.parent_coin_info)
+        .await?
+        .ok_or(anyhow!("Parent of ephemeral not found"))?;
+
+    // Now, the conditions on the spend of the direct parent should enable us to find the other parent of the ephemeral coin
+    // linked by CREATE_COIN_ANNOUNCEMENT/ASSERT_COIN_ANNOUNCEMENT
+    let conditions = conditions_for_coin(client, &direct_parent).await?;
+
+    // Now, we need to find the CREATE_COIN_ANNOUNCEMENT
+    let create_coin_announcements = conditions
+        .into_iter()
+        .filter_map(Condition::into_create_coin_announcement);
+
+    let mut input_coins: Vec<CoinRecord> = vec![];
+
+    for announcement in create_coin_announcements {
+        let mut hasher = Sha256::new();
+        hasher.update(eph_coin.coin.parent_coin_info);
+        hasher.update(&announcement.message);
+        let message = hasher.finalize().to_vec();
+
+        // now we have to find coins with ASSERT_COIN_ANNOUNCEMENT of `message`
+        // Get all the coin creations in the block, and find the matching assert
+        let block = client
+            .get_block_record_by_height(eph_coin.confirmed_block_index)
+            .await?;
+        let (_additions, removals) = client
+            .get_additions_and_removals(&block.header_hash)
+            .await?;
+        for removal in removals {
+            let conditions = conditions_for_coin(client, &removal).await?;
+            let assert_coin_announcements = conditions
+                .into_iter()
+                .filter_map(Condition::into_assert_coin_announcement);
+            for assert_coin_announcement in assert_coin_announcements {
+                if assert_coin_announcement.announcement_id[..] == message {
+                    input_coins.push(removal.clone());
+                }
+            }
+        }
+    }
+
+    if input_coins.len() != 1 {
+        anyhow::bail!("Unexpected number of input coins found");
+    }
+
+    // At this point, we found the parent of the NFT that directly leads back to the metadata (without tracing asserts)
+    input_coins.pop().ok_or(anyhow!("Missing input coin"))
+}
+
+async fn advance_parent(client: &FullnodeClient, coin: &CoinRecord) -> Result<CoinRecord> {
+    client
+        .get_coin_record_by_name(&coin.coin.parent_coin_info)
+        .await?
+        .ok_or(anyhow!("No coin found"))
+}
@@ -1,18 +1,254 @@
 use crate::chia::coins::conditions_for_puzz_solution;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chia::clvm_traits::{FromClvm, ToClvm};
 use chia::protocol::{Bytes, Program};
 use chia_wallet_sdk::Condition;
+use clvmr::Allocator;
+use std::fmt;
+use std::str::FromStr;
 
-pub fn parse_memos(solution_program: &Program, reveal_program: &Program) -> Result<Option<Bytes>> {
+/// Controls which parts of a spend's conditions are treated as the data payload
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemoStrategy {
+    /// The first memo of the first `CREATE_COIN`. This is how the Chia Gods collection was inscribed
+    #[default]
+    FirstMemo,
+    /// Every memo of the first `CREATE_COIN`, concatenated
+    AllMemos,
+    /// Every memo of the `CREATE_COIN` at the given output index, concatenated
+    OutputIndex(usize),
+    /// The payloads of every `REMARK` condition, concatenated
+    Remark,
+}
+
+impl FromStr for MemoStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "first" => Ok(Self::FirstMemo),
+            "all" => Ok(Self::AllMemos),
+            "remark" => Ok(Self::Remark),
+            _ => {
+                if let Some(index) = s.strip_prefix("output:") {
+                    return Ok(Self::OutputIndex(index.parse()?));
+                }
+                anyhow::bail!(
+                    "Unknown memo strategy `{s}`, expected one of: first, all, output:<index>, remark"
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for MemoStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FirstMemo => write!(f, "first"),
+            Self::AllMemos => write!(f, "all"),
+            Self::OutputIndex(index) => write!(f, "output:{index}"),
+            Self::Remark => write!(f, "remark"),
+        }
+    }
+}
+
+pub fn parse_memos(
+    solution_program: &Program,
+    reveal_program: &Program,
+    strategy: MemoStrategy,
+) -> Result<Option<Bytes>> {
     let conditions = conditions_for_puzz_solution(solution_program, reveal_program)?;
-    Ok(parse_memos_from_conditions(conditions))
+    parse_memos_from_conditions(conditions, strategy)
+}
+
+/// The data payload `strategy` picks out of a spend's conditions, or `None` if the spend doesn't
+/// carry one
+///
+/// # Errors
+///
+/// Fails if a `REMARK` condition's payload isn't a list of atoms.
+pub fn parse_memos_from_conditions(
+    conditions: Vec<Condition<Program>>,
+    strategy: MemoStrategy,
+) -> Result<Option<Bytes>> {
+    let memos = match strategy {
+        MemoStrategy::FirstMemo => {
+            // A CREATE_COIN without memos carries no data, the same as no CREATE_COIN at all
+            let create_coin = conditions.into_iter().find_map(Condition::into_create_coin);
+            return Ok(create_coin.and_then(|create_coin| create_coin.memos.into_iter().next()));
+        }
+        MemoStrategy::AllMemos => {
+            let Some(create_coin) = conditions.into_iter().find_map(Condition::into_create_coin)
+            else {
+                return Ok(None);
+            };
+            concat_memos(&create_coin.memos)
+        }
+        MemoStrategy::OutputIndex(index) => {
+            let Some(create_coin) = conditions
+                .into_iter()
+                .filter_map(Condition::into_create_coin)
+                .nth(index)
+            else {
+                return Ok(None);
+            };
+            concat_memos(&create_coin.memos)
+        }
+        MemoStrategy::Remark => conditions
+            .into_iter()
+            .filter_map(Condition::into_remark)
+            .map(|remark| remark_payload(&remark.rest))
+            .collect::<Result<Vec<_>>>()?
+            .concat(),
+    };
+
+    if memos.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Bytes::new(memos)))
+}
+
+/// Concatenates the atoms following the `REMARK` opcode
+fn remark_payload(rest: &Program) -> Result<Vec<u8>> {
+    let mut allocator = Allocator::new();
+    let ptr = rest.to_clvm(&mut allocator)?;
+    let atoms = Vec::<Bytes>::from_clvm(&allocator, ptr)
+        .map_err(|err| anyhow!("REMARK payload isn't a list of atoms: {err}"))?;
+    Ok(concat_memos(&atoms))
 }
 
-pub fn parse_memos_from_conditions(conditions: Vec<Condition>) -> Option<Bytes> {
-    let create_coin = conditions.into_iter().find_map(Condition::into_create_coin);
-    let memos = create_coin.map(|create_coin| create_coin.memos);
-    match memos {
-        Some(vec) if !vec.is_empty() => Some(vec.into_iter().next().unwrap()), // First item
-        Some(_) | None => None, // Vec exists but is empty | No memos (Option is None)
+fn concat_memos(memos: &[Bytes]) -> Vec<u8> {
+    memos
+        .iter()
+        .flat_map(|memo| memo.as_ref().iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chia::protocol::Bytes32;
+    use chia_wallet_sdk::{CreateCoin, Remark};
+
+    /// `parse_memos_from_conditions` as it was before memo strategies, which `FirstMemo` must match
+    fn baseline(conditions: Vec<Condition<Program>>) -> Option<Bytes> {
+        let create_coin = conditions.into_iter().find_map(Condition::into_create_coin);
+        let memos = create_coin.map(|create_coin| create_coin.memos);
+        match memos {
+            Some(vec) if !vec.is_empty() => vec.into_iter().next(),
+            Some(_) | None => None,
+        }
+    }
+
+    fn create_coin(memos: &[&[u8]]) -> Condition<Program> {
+        let memos = memos.iter().map(|memo| Bytes::new(memo.to_vec())).collect();
+        Condition::CreateCoin(CreateCoin::new(Bytes32::new([1; 32]), 1, memos))
+    }
+
+    fn remark(rest: impl ToClvm<Allocator>) -> Condition<Program> {
+        let mut allocator = Allocator::new();
+        let node = rest.to_clvm(&mut allocator).unwrap();
+        Condition::Remark(Remark::new(Program::from_clvm(&allocator, node).unwrap()))
+    }
+
+    fn atoms(atoms: &[&[u8]]) -> Vec<Bytes> {
+        atoms.iter().map(|atom| Bytes::new(atom.to_vec())).collect()
+    }
+
+    fn parse(conditions: &[Condition<Program>], strategy: MemoStrategy) -> Option<Vec<u8>> {
+        parse_memos_from_conditions(conditions.to_vec(), strategy)
+            .unwrap()
+            .map(|memo| memo.to_vec())
+    }
+
+    fn spends() -> Vec<Vec<Condition<Program>>> {
+        vec![
+            vec![],
+            vec![remark(atoms(&[b"note"]))],
+            vec![create_coin(&[])],
+            vec![create_coin(&[b"first", b"second"])],
+            vec![
+                remark(atoms(&[b"note"])),
+                create_coin(&[b"one"]),
+                create_coin(&[b"two"]),
+            ],
+            vec![create_coin(&[]), create_coin(&[b"later"])],
+        ]
+    }
+
+    #[test]
+    fn first_memo_matches_the_baseline() {
+        for conditions in spends() {
+            assert_eq!(
+                parse_memos_from_conditions(conditions.clone(), MemoStrategy::FirstMemo).unwrap(),
+                baseline(conditions)
+            );
+        }
+        assert_eq!(
+            parse(&spends()[3], MemoStrategy::FirstMemo),
+            Some(b"first".to_vec())
+        );
+        assert_eq!(MemoStrategy::default(), MemoStrategy::FirstMemo);
+    }
+
+    #[test]
+    fn all_memos_of_the_first_create_coin() {
+        let conditions = [create_coin(&[b"ab", b"cd"]), create_coin(&[b"ef"])];
+        assert_eq!(
+            parse(&conditions, MemoStrategy::AllMemos),
+            Some(b"abcd".to_vec())
+        );
+        assert_eq!(parse(&[], MemoStrategy::AllMemos), None);
+        assert_eq!(parse(&[create_coin(&[])], MemoStrategy::AllMemos), None);
+    }
+
+    #[test]
+    fn memos_of_an_output_index() {
+        let conditions = [
+            remark(atoms(&[b"note"])),
+            create_coin(&[b"ab"]),
+            create_coin(&[b"cd", b"ef"]),
+        ];
+        assert_eq!(
+            parse(&conditions, MemoStrategy::OutputIndex(0)),
+            Some(b"ab".to_vec())
+        );
+        assert_eq!(
+            parse(&conditions, MemoStrategy::OutputIndex(1)),
+            Some(b"cdef".to_vec())
+        );
+        assert_eq!(parse(&conditions, MemoStrategy::OutputIndex(2)), None);
+    }
+
+    #[test]
+    fn remark_payloads() {
+        let conditions = [
+            remark(atoms(&[b"ab", b"cd"])),
+            create_coin(&[b"memo"]),
+            remark(atoms(&[b"ef"])),
+        ];
+        assert_eq!(
+            parse(&conditions, MemoStrategy::Remark),
+            Some(b"abcdef".to_vec())
+        );
+        assert_eq!(
+            parse(&[create_coin(&[b"memo"])], MemoStrategy::Remark),
+            None
+        );
+    }
+
+    #[test]
+    fn remark_that_isnt_a_list_of_atoms_fails() {
+        let conditions = vec![remark((atoms(&[b"ab"]), ()))];
+        assert!(parse_memos_from_conditions(conditions, MemoStrategy::Remark).is_err());
+    }
+
+    #[test]
+    fn strategies_parse_and_display() {
+        for text in ["first", "all", "output:3", "remark"] {
+            assert_eq!(text.parse::<MemoStrategy>().unwrap().to_string(), text);
+        }
+        assert!("output:x".parse::<MemoStrategy>().is_err());
+        assert!("last".parse::<MemoStrategy>().is_err());
     }
 }
@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use flate2::read::MultiGzDecoder;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use std::fmt;
+use std::io::Read;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decodes a metadata payload: a JSON array, either uncompressed or compressed with gzip (any
+/// number of concatenated members) or zstd. Entries can be JSON objects, or base64 encoded
+/// documents in the standard or URL-safe alphabet, with or without padding.
+///
+/// The payload is decompressed and parsed as a stream, one entry at a time.
+///
+/// # Errors
+///
+/// Fails if the payload can't be decompressed or isn't a JSON array. Problems with a single entry
+/// are returned in that entry's place instead.
+pub fn decode_metadata(payload: &[u8]) -> Result<Vec<Result<Vec<u8>>>> {
+    let reader: Box<dyn Read + '_> = if payload.starts_with(&GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(payload))
+    } else if payload.starts_with(&ZSTD_MAGIC) {
+        Box::new(zstd::stream::read::Decoder::new(payload)?)
+    } else {
+        Box::new(payload)
+    };
+
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let entries = deserializer.deserialize_seq(EntriesVisitor)?;
+    deserializer.end()?;
+    Ok(entries)
+}
+
+struct EntriesVisitor;
+
+impl<'de> Visitor<'de> for EntriesVisitor {
+    type Value = Vec<Result<Vec<u8>>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON array of metadata entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entries = vec![];
+        while let Some(entry) = seq.next_element::<Value>()? {
+            entries.push(decode_entry(entry));
+        }
+        Ok(entries)
+    }
+}
+
+fn decode_entry(entry: Value) -> Result<Vec<u8>> {
+    match entry {
+        Value::Object(_) => Ok(serde_json::to_vec_pretty(&entry)?),
+        Value::String(encoded) => decode_base64(&encoded),
+        _ => anyhow::bail!("Entry is neither a base64 string nor a JSON object"),
+    }
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>> {
+    let encoded: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+    [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(&encoded).ok())
+        .ok_or(anyhow!("Entry is not valid base64"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decoded(payload: &[u8]) -> Vec<Vec<u8>> {
+        decode_metadata(payload)
+            .unwrap()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn raw_json() {
+        assert_eq!(
+            decoded(br#"["eyJhIjoxfQ==", "eyJiIjoyfQ=="]"#),
+            [br#"{"a":1}"#.to_vec(), br#"{"b":2}"#.to_vec()]
+        );
+    }
+
+    #[test]
+    fn gzip_with_several_members() {
+        let mut payload = gzip(br#"["eyJhIjoxfQ==","#);
+        payload.extend(gzip(br#""eyJiIjoyfQ=="]"#));
+        assert_eq!(
+            decoded(&payload),
+            [br#"{"a":1}"#.to_vec(), br#"{"b":2}"#.to_vec()]
+        );
+    }
+
+    #[test]
+    fn zstd() {
+        let payload = zstd::encode_all(&br#"["eyJhIjoxfQ=="]"#[..], 0).unwrap();
+        assert!(payload.starts_with(&ZSTD_MAGIC));
+        assert_eq!(decoded(&payload), [br#"{"a":1}"#.to_vec()]);
+    }
+
+    #[test]
+    fn url_safe_and_unpadded_base64() {
+        // 0xfb 0xff is "+/8=" in the standard alphabet
+        assert_eq!(
+            decoded(br#"["-_8=", "-_8", "+/8", "eyJhIjoxfQ"]"#),
+            [
+                vec![0xfb, 0xff],
+                vec![0xfb, 0xff],
+                vec![0xfb, 0xff],
+                br#"{"a":1}"#.to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn object_entries() {
+        let entries = decoded(br#"[{"format": "CHIP-0007", "name": "One"}]"#);
+        let document: Value = serde_json::from_slice(&entries[0]).unwrap();
+        assert_eq!(document["format"], "CHIP-0007");
+        assert_eq!(document["name"], "One");
+    }
+
+    #[test]
+    fn bad_entries_dont_fail_the_rest() {
+        let entries = decode_metadata(br#"["eyJhIjoxfQ==", 42, "not base64!", {"b": 2}]"#).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].as_ref().unwrap(), br#"{"a":1}"#);
+        assert!(entries[1].is_err());
+        assert!(entries[2].is_err());
+        assert!(entries[3].is_ok());
+    }
+
+    #[test]
+    fn payload_that_isnt_an_array_fails() {
+        assert!(decode_metadata(br#"{"a": 1}"#).is_err());
+        assert!(decode_metadata(&gzip(b"not json")).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::chia::coins::{conditions_for_coin, to_chia_coin};
+use anyhow::{anyhow, Result};
+use chia::clvm_traits::{FromClvm, ToClvm};
+use chia::protocol::Program;
+use chia::puzzles::nft::NftMetadata;
+use chia::traits::Streamable;
+use chia_wallet_sdk::{Condition, DriverError, HashedPtr, Nft, NftInfo, Puzzle};
+use clvmr::Allocator;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::coin::Coin;
+use dg_xch_core::blockchain::coin_record::CoinRecord;
+use dg_xch_core::blockchain::coin_spend::CoinSpend;
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
+
+pub struct NftOwnership {
+    pub launcher_id: Bytes32,
+    pub current_coin: CoinRecord,
+    pub p2_puzzle_hash: Bytes32,
+    pub owner_did: Option<Bytes32>,
+    /// The current on-chain metadata, if it follows the standard NFT metadata format
+    pub metadata: Option<NftMetadata>,
+    pub spends: u32,
+    /// Number of spends that changed the owner puzzle hash, including the eve spend
+    pub transfers: u32,
+}
+
+struct NftSpend {
+    parent_p2_puzzle_hash: Bytes32,
+    child_coin: Coin,
+    child_p2_puzzle_hash: Bytes32,
+    child_owner_did: Option<Bytes32>,
+    child_metadata: Option<NftMetadata>,
+}
+
+/// Follows the NFT singleton forward from its launcher, through every spend, to the current unspent coin
+pub async fn trace_nft(client: &FullnodeClient, launcher_id: &Bytes32) -> Result<NftOwnership> {
+    let launcher = client
+        .get_coin_record_by_name(launcher_id)
+        .await?
+        .ok_or(anyhow!("Launcher Coin Record not found."))?;
+    if launcher.spent_block_index == 0 {
+        anyhow::bail!("Launcher coin has not been spent, so the NFT was never minted");
+    }
+
+    // The launcher creates exactly one coin: the eve NFT coin
+    let eve = conditions_for_coin(client, &launcher)
+        .await?
+        .into_iter()
+        .find_map(Condition::into_create_coin)
+        .ok_or(anyhow!("Launcher spend did not create the eve coin"))?;
+    let mut current_coin = Coin {
+        parent_coin_info: launcher.coin.coin_id(),
+        puzzle_hash: Bytes32::new(eve.puzzle_hash.as_ref()),
+        amount: eve.amount,
+    };
+
+    let mut p2_puzzle_hash = None;
+    let mut owner_did = None;
+    let mut metadata = None;
+    let mut spends = 0;
+    let mut transfers = 0;
+
+    loop {
+        let coin_record = client
+            .get_coin_record_by_name(&current_coin.name())
+            .await?
+            .ok_or(anyhow!("Unable to get NFT coin {}", current_coin.name()))?;
+
+        if coin_record.spent_block_index == 0 {
+            let p2_puzzle_hash = p2_puzzle_hash.ok_or(anyhow!(
+                "The eve coin has not been spent, so its owner is not revealed on chain yet"
+            ))?;
+            return Ok(NftOwnership {
+                launcher_id: *launcher_id,
+                current_coin: coin_record,
+                p2_puzzle_hash,
+                owner_did,
+                metadata,
+                spends,
+                transfers,
+            });
+        }
+
+        let puzz_solution = client
+            .get_puzzle_and_solution(&current_coin.name(), coin_record.spent_block_index)
+            .await?;
+        let spend = parse_nft_spend(&coin_record.coin, &puzz_solution)?;
+
+        spends += 1;
+        if spend.parent_p2_puzzle_hash != spend.child_p2_puzzle_hash {
+            transfers += 1;
+        }
+        p2_puzzle_hash = Some(spend.child_p2_puzzle_hash);
+        owner_did = spend.child_owner_did;
+        metadata = spend.child_metadata;
+        current_coin = spend.child_coin;
+    }
+}
+
+fn parse_nft_spend(coin: &Coin, puzz_solution: &CoinSpend) -> Result<NftSpend> {
+    let mut allocator = Allocator::new();
+    let puzzle_program = Program::from_bytes(&puzz_solution.puzzle_reveal.to_bytes())?;
+    let solution_program = Program::from_bytes(&puzz_solution.solution.to_bytes())?;
+    let puzzle_ptr = puzzle_program.to_clvm(&mut allocator)?;
+    let solution_ptr = solution_program.to_clvm(&mut allocator)?;
+    let puzzle = Puzzle::parse(&allocator, puzzle_ptr);
+
+    let (parent_info, _p2_puzzle) = NftInfo::<HashedPtr>::parse(&allocator, puzzle)?
+        .ok_or(anyhow!("Coin {} is not an NFT", coin.name()))?;
+
+    let child = match Nft::<HashedPtr>::parse_child(
+        &mut allocator,
+        to_chia_coin(coin),
+        puzzle,
+        solution_ptr,
+    ) {
+        Ok(Some(child)) => child,
+        Ok(None) => anyhow::bail!("Coin {} is not an NFT", coin.name()),
+        Err(DriverError::MissingChild) => {
+            anyhow::bail!("NFT was melted when coin {} was spent", coin.name())
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let child_metadata = NftMetadata::from_clvm(&allocator, child.info.metadata.ptr()).ok();
+
+    Ok(NftSpend {
+        parent_p2_puzzle_hash: Bytes32::new(parent_info.p2_puzzle_hash.as_ref()),
+        child_coin: Coin {
+            parent_coin_info: coin.name(),
+            puzzle_hash: Bytes32::new(child.coin.puzzle_hash.as_ref()),
+            amount: child.coin.amount,
+        },
+        child_p2_puzzle_hash: Bytes32::new(child.info.p2_puzzle_hash.as_ref()),
+        child_owner_did: child
+            .info
+            .current_owner
+            .map(|did| Bytes32::new(did.as_ref())),
+        child_metadata,
+    })
+}
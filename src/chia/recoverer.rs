@@ -0,0 +1,380 @@
+use crate::assemble::{CollectionAssembler, ImageAssembler, MetadataAssembler};
+use crate::chia::cache::SpendCache;
+use crate::chia::coins::{next_coin_in_chain, released_amount, run_spend};
+use crate::chia::locate::{locate, GapMemo, LocatedData};
+use crate::chia::memo::{parse_memos_from_conditions, MemoStrategy};
+use crate::chia::metadata::decode_metadata;
+use crate::Framing;
+use anyhow::{anyhow, Result};
+use chia::protocol::{Bytes, Program};
+use chia::traits::Streamable;
+use chia_wallet_sdk::Condition;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::coin_record::CoinRecord;
+use dg_xch_core::blockchain::coin_spend::CoinSpend;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::pin::pin;
+use std::sync::Arc;
+use tracing::{debug, info, instrument, warn};
+
+/// An image read back from the chain
+pub struct RecoveredImage {
+    /// The coin whose spend starts the image
+    pub start_coin: Bytes32,
+    /// The coin whose spend finishes the image
+    pub end_coin: Bytes32,
+    /// Position of the image in its collection, starting at 1
+    pub index: usize,
+    /// The filename inscribed with the image, if there is one
+    pub filename: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// A spent coin in a data chain, and the data payload of its spend
+pub struct ChainLink {
+    pub coin: CoinRecord,
+    pub memo: Option<Bytes>,
+    /// An estimate of the cost the spend added to its block, from the puzzle, its conditions and
+    /// the size of the puzzle reveal and solution
+    pub estimated_cost: u64,
+    /// The mojos the spend didn't pass on to the coins it created. This is only the spend's fee
+    /// when no other coin in its spend bundle pays or takes mojos
+    pub released: u64,
+    /// The size of the serialized puzzle reveal and solution
+    pub spend_bytes: usize,
+    /// Every condition the spend created, which the memo was picked from
+    pub conditions: Vec<Condition<Program>>,
+}
+
+impl ChainLink {
+    /// The payload of the spend, which every coin in a data chain must have
+    ///
+    /// # Errors
+    ///
+    /// Fails if the spend has no payload for the memo strategy.
+    pub fn require_memo(&self) -> Result<&Bytes> {
+        self.memo
+            .as_ref()
+            .ok_or(anyhow!("Coin {} has no memo", self.coin.coin.name()))
+    }
+}
+
+/// Receives progress while a [`Recoverer`] walks the chain. Every method does nothing by default
+pub trait RecoveryObserver: Send + Sync {
+    /// Called for every data coin read, with the number of bytes of data it held
+    fn coin_walked(&self, _coin_id: &Bytes32, _bytes: usize) {}
+
+    /// Called once an image has been read in full
+    fn image_recovered(&self, _image: &RecoveredImage) {}
+
+    /// Called when [`Recoverer::locate`] finds the spend between the metadata and the mint, before
+    /// it walks on to the collection data
+    fn gap_found(&self, _gap: &GapMemo) {}
+}
+
+struct NoObserver;
+
+impl RecoveryObserver for NoObserver {}
+
+/// Recovers images, collections, and metadata inscribed on chain, through a full node
+pub struct Recoverer {
+    client: FullnodeClient,
+    memo_strategy: MemoStrategy,
+    framing: Framing,
+    observer: Arc<dyn RecoveryObserver>,
+    cache: Option<SpendCache>,
+}
+
+enum WalkState {
+    Start(Bytes32),
+    /// The last coin walked, and the conditions of its spend
+    Next(CoinRecord, Vec<Condition<Program>>),
+    Done,
+}
+
+struct CollectionState<'a> {
+    links: BoxStream<'a, Result<ChainLink>>,
+    assembler: CollectionAssembler,
+    index: usize,
+}
+
+impl Recoverer {
+    #[must_use]
+    pub fn new(client: FullnodeClient, memo_strategy: MemoStrategy) -> Self {
+        Self {
+            client,
+            memo_strategy,
+            framing: Framing::default(),
+            observer: Arc::new(NoObserver),
+            cache: None,
+        }
+    }
+
+    /// Recognizes collections and metadata by the markers of `framing`, instead of the Chia Gods
+    /// ones
+    #[must_use]
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Reports progress to `observer` during recoveries
+    #[must_use]
+    pub fn with_observer(mut self, observer: Arc<dyn RecoveryObserver>) -> Self {
+        self.observer = observer;
+        self
+    }
+
+    /// Keeps the spent coins read while walking chains in `cache`, and reads them from there
+    /// instead of the node
+    #[must_use]
+    pub fn with_cache(mut self, cache: SpendCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    #[must_use]
+    pub fn client(&self) -> &FullnodeClient {
+        &self.client
+    }
+
+    #[must_use]
+    pub fn memo_strategy(&self) -> MemoStrategy {
+        self.memo_strategy
+    }
+
+    #[must_use]
+    pub fn framing(&self) -> &Framing {
+        &self.framing
+    }
+
+    /// Walks the data chain starting at `start_coin`, following the child that continues the
+    /// chain out of each spend.
+    ///
+    /// The stream ends at the first unspent coin. It is lazy, so the walk stops as soon as the
+    /// stream is dropped. The first error ends the stream.
+    pub fn walk_chain(&self, start_coin: &Bytes32) -> impl Stream<Item = Result<ChainLink>> + '_ {
+        stream::unfold(WalkState::Start(*start_coin), move |state| {
+            self.walk_step(state)
+        })
+    }
+
+    /// Recovers the image whose data starts in the spend of `coin_id`
+    ///
+    /// # Errors
+    ///
+    /// Fails if the coin doesn't start an image, or the chain ends before the image does.
+    #[instrument(name = "image", skip_all, fields(start_coin = %coin_id))]
+    pub async fn recover_image(&self, coin_id: &Bytes32) -> Result<RecoveredImage> {
+        let mut links = pin!(self.walk_chain(coin_id));
+        let mut assembler = ImageAssembler::new().with_framing(self.framing.clone());
+        while let Some(link) = links.next().await {
+            let link = link?;
+            let memo = link.require_memo()?;
+            self.observer
+                .coin_walked(&link.coin.coin.name(), memo.len());
+
+            if let Some(assembled) = assembler.push(memo)? {
+                let image = RecoveredImage {
+                    start_coin: *coin_id,
+                    end_coin: link.coin.coin.name(),
+                    index: 1,
+                    filename: assembled.filename,
+                    data: assembled.data,
+                };
+                self.observer.image_recovered(&image);
+                return Ok(image);
+            }
+        }
+
+        anyhow::bail!("No more data available on chain, but did not reach end of the image!");
+    }
+
+    /// Recovers every image of the collection starting at `start_coin`, in order.
+    ///
+    /// The stream ends after the image marked as the end of the collection, or early if the chain
+    /// stops before it. The first error ends the stream.
+    pub fn recover_collection(
+        &self,
+        start_coin: &Bytes32,
+    ) -> impl Stream<Item = Result<RecoveredImage>> + '_ {
+        let state = CollectionState {
+            links: self.walk_chain(start_coin).boxed(),
+            assembler: CollectionAssembler::new().with_framing(self.framing.clone()),
+            index: 0,
+        };
+        stream::unfold(Some(state), move |state| async move {
+            let mut state = state?;
+            match self.next_collection_image(&mut state).await {
+                Ok(Some(image)) => {
+                    let done = state.assembler.is_finished();
+                    Some((Ok(image), (!done).then_some(state)))
+                }
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Recovers the metadata inscribed starting in the spend of `coin_id`, following the chain
+    /// until the end of the metadata. Returns the JSON document of each NFT in the collection in
+    /// order, and an error in the place of any entry that can't be decoded
+    ///
+    /// # Errors
+    ///
+    /// Fails if the coin doesn't start the metadata, the chain ends before the metadata does, or
+    /// the metadata can't be decompressed and parsed.
+    pub async fn recover_metadata(&self, coin_id: &Bytes32) -> Result<Vec<Result<Vec<u8>>>> {
+        let mut links = pin!(self.walk_chain(coin_id));
+        let mut assembler = MetadataAssembler::new().with_framing(self.framing.clone());
+        while let Some(link) = links.next().await {
+            let link = link?;
+            let memo = link.require_memo()?;
+            self.observer
+                .coin_walked(&link.coin.coin.name(), memo.len());
+
+            if let Some(payload) = assembler.push(memo)? {
+                return decode_metadata(&payload);
+            }
+        }
+
+        anyhow::bail!("No more data available on chain, but did not reach end of the metadata!");
+    }
+
+    /// Walks back from an NFT to the coins holding its collection's metadata and images
+    ///
+    /// # Errors
+    ///
+    /// Fails if the NFT wasn't minted from inscribed data laid out the way this tool expects.
+    pub async fn locate(&self, launcher_id: &Bytes32) -> Result<LocatedData> {
+        locate(
+            &self.client,
+            launcher_id,
+            self.memo_strategy,
+            &self.framing,
+            self.observer.as_ref(),
+        )
+        .await
+    }
+
+    async fn walk_step(&self, state: WalkState) -> Option<(Result<ChainLink>, WalkState)> {
+        let coin = match state {
+            WalkState::Start(coin_id) => self.spent_coin(&coin_id).await,
+            WalkState::Next(parent, conditions) => {
+                match next_coin_in_chain(&parent.coin, &conditions) {
+                    Ok(child) => self.spent_coin(&child.name()).await,
+                    Err(err) => Err(err),
+                }
+            }
+            WalkState::Done => return None,
+        };
+        let coin = match coin {
+            Ok(Some(coin)) => coin,
+            Ok(None) => return None,
+            Err(err) => return Some((Err(err), WalkState::Done)),
+        };
+
+        match self.read_link(coin).await {
+            Ok(link) => {
+                let next = WalkState::Next(link.coin.clone(), link.conditions.clone());
+                Some((Ok(link), next))
+            }
+            Err(err) => Some((Err(err), WalkState::Done)),
+        }
+    }
+
+    /// Fetches a coin's record, or `None` if it hasn't been spent
+    async fn spent_coin(&self, coin_id: &Bytes32) -> Result<Option<CoinRecord>> {
+        if let Some(cache) = &self.cache {
+            if let Some(coin) = cache.coin_record(coin_id).await {
+                return Ok(Some(coin));
+            }
+        }
+        let coin = self
+            .client
+            .get_coin_record_by_name(coin_id)
+            .await?
+            .ok_or(anyhow!("Unable to get coin {coin_id}"))?;
+        if let Some(cache) = &self.cache {
+            cache.store_coin_record(&coin).await;
+        }
+        Ok((coin.spent_block_index > 0).then_some(coin))
+    }
+
+    async fn coin_spend(&self, coin: &CoinRecord) -> Result<CoinSpend> {
+        if let Some(cache) = &self.cache {
+            if let Some(spend) = cache.coin_spend(&coin.coin.name()).await {
+                return Ok(spend);
+            }
+        }
+        let spend = self
+            .client
+            .get_puzzle_and_solution(&coin.coin.name(), coin.spent_block_index)
+            .await?;
+        if let Some(cache) = &self.cache {
+            cache.store_coin_spend(coin, &spend).await;
+        }
+        Ok(spend)
+    }
+
+    async fn read_link(&self, coin: CoinRecord) -> Result<ChainLink> {
+        let spend = self.coin_spend(&coin).await?;
+        let puzzle = Program::from_bytes(&spend.puzzle_reveal.to_bytes())?;
+        let solution = Program::from_bytes(&spend.solution.to_bytes())?;
+        let output = run_spend(&solution, &puzzle)?;
+        let conditions = output.conditions;
+        let memo = parse_memos_from_conditions(conditions.clone(), self.memo_strategy)?;
+        let link = ChainLink {
+            released: released_amount(&coin.coin, &conditions),
+            estimated_cost: output.estimated_cost,
+            spend_bytes: puzzle.len() + solution.len(),
+            coin,
+            memo,
+            conditions,
+        };
+        debug!(
+            coin = %link.coin.coin.name(),
+            bytes = link.memo.as_ref().map_or(0, Bytes::len),
+            estimated_cost = link.estimated_cost,
+            "Read chain link"
+        );
+        Ok(link)
+    }
+
+    async fn next_collection_image(
+        &self,
+        state: &mut CollectionState<'_>,
+    ) -> Result<Option<RecoveredImage>> {
+        let mut start_coin = None;
+        while let Some(link) = state.links.next().await {
+            let link = link?;
+            let memo = link.require_memo()?;
+            self.observer
+                .coin_walked(&link.coin.coin.name(), memo.len());
+            if state.assembler.at_image_start() {
+                start_coin = Some(link.coin.coin.name());
+            }
+
+            if let Some(assembled) = state.assembler.push(memo)? {
+                state.index += 1;
+                let image = RecoveredImage {
+                    start_coin: start_coin.unwrap_or(link.coin.coin.name()),
+                    end_coin: link.coin.coin.name(),
+                    index: state.index,
+                    filename: assembled.filename,
+                    data: assembled.data,
+                };
+                self.observer.image_recovered(&image);
+                if state.assembler.is_finished() {
+                    info!(images = state.index, "Reached end of collection");
+                }
+                return Ok(Some(image));
+            }
+        }
+
+        warn!("No more data available on chain, but did not reach end of collection");
+        Ok(None)
+    }
+}
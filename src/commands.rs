@@ -1,4 +1,25 @@
+pub mod attest;
+pub mod browse;
+pub mod compare;
+pub mod dump_chain;
+pub mod export_car;
+pub mod find_inscriptions;
+pub mod inspect_coin;
+pub mod known;
 pub mod locate_nft_data;
+pub mod manifest;
+pub mod match_images;
+pub mod mint_kit;
+pub mod prove_inclusion;
+pub mod rebuild;
 pub mod recover_collection;
 pub mod recover_image;
+pub mod recover_known;
 pub mod recover_metadata;
+pub mod report;
+pub mod scan;
+pub mod serve;
+pub mod trace_nft;
+pub mod verify_attestation;
+pub mod verify_inclusion;
+pub mod watch;
@@ -0,0 +1,75 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::{ArgGroup, Args};
+use recovery_tools::attestation::{key_from_hex, key_from_mnemonic, Attestation, ATTESTATION_FILE};
+use recovery_tools::coin_id_from_string;
+use recovery_tools::manifest::Manifest;
+use std::path::PathBuf;
+use tokio::fs;
+
+#[derive(Args)]
+#[command(about = "Signs the manifest of a recovery and the coins it came from with a BLS key")]
+#[command(group(ArgGroup::new("key").required(true).args(["mnemonic_file", "key_file"])))]
+pub struct Attest {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The coin ID at the start of the collection [default: the profile's start_coin]
+    #[arg(short, long)]
+    coin: Option<String>,
+
+    /// The coin ID that contains the metadata [default: the profile's metadata_coin, if it has one]
+    #[arg(long)]
+    metadata_coin: Option<String>,
+
+    /// A file holding the 24 word mnemonic to sign with. The first hardened wallet key is used
+    #[arg(long)]
+    mnemonic_file: Option<PathBuf>,
+
+    /// A file holding the secret key to sign with, in hex
+    #[arg(long)]
+    key_file: Option<PathBuf>,
+
+    /// The file to write the attestation to [default: attestation.json in the directory]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl Attest {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        let key = match (&self.mnemonic_file, &self.key_file) {
+            (Some(file), _) => key_from_mnemonic(&fs::read_to_string(file).await?)?,
+            (None, Some(file)) => key_from_hex(&fs::read_to_string(file).await?)?,
+            (None, None) => unreachable!("clap requires one of the key options"),
+        };
+
+        let mut coins = vec![coin_id_from_string(&ctx.start_coin(self.coin.as_ref())?)?];
+        if let Ok(coin) = ctx.metadata_coin(self.metadata_coin.as_ref()) {
+            coins.push(coin_id_from_string(&coin)?);
+        }
+
+        let manifest = Manifest::from_dir(dir)?;
+        if manifest.files.is_empty() {
+            anyhow::bail!("No recovered files in {}", dir.display());
+        }
+        let attestation = Attestation::sign(&manifest, &coins, &key);
+        let file = self
+            .output
+            .clone()
+            .unwrap_or_else(|| dir.join(ATTESTATION_FILE));
+        fs::write(&file, serde_json::to_vec_pretty(&attestation)?).await?;
+        output(format!(
+            "wrote {} public_key={} fingerprint={} merkle_root={} files={} coins={}",
+            file.display(),
+            attestation.public_key,
+            key.public_key().get_fingerprint(),
+            attestation.merkle_root,
+            manifest.files.len(),
+            coins.len()
+        ));
+        anyhow::Ok(())
+    }
+}
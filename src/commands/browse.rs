@@ -0,0 +1,510 @@
+use crate::config::Context;
+use crate::logging;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use futures::StreamExt;
+use indicatif::HumanBytes;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use recovery_tools::archive::{image_filename, list_files, IMAGES_DIR, METADATA_DIR};
+use recovery_tools::chia::{RecoveredImage, Recoverer};
+use recovery_tools::known::find_known;
+use recovery_tools::manifest::{Manifest, MANIFEST_FILE};
+use recovery_tools::{coin_id_from_string, sha256};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use tokio::fs;
+use tokio::sync::{mpsc, watch};
+use tracing::{info, warn};
+
+/// How far Page Up and Page Down move the selection
+const PAGE: usize = 10;
+
+#[derive(Args)]
+#[command(about = "Browse a collection image by image, recovering images as they're scrolled to")]
+pub struct Browse {
+    /// The coin ID at the start of the collection [default: the profile's start_coin]
+    #[arg(short, long)]
+    coin: Option<String>,
+
+    /// The coin ID that contains the metadata, read when the output directory has no recovered
+    /// metadata [default: the profile's metadata_coin]
+    #[arg(long)]
+    metadata_coin: Option<String>,
+
+    /// The number of images to recover ahead of the selected one
+    #[arg(long, default_value_t = 2)]
+    prefetch: usize,
+}
+
+impl Browse {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let coin = ctx.start_coin(self.coin.as_ref())?;
+        let start_coin = coin_id_from_string(&coin)?;
+        info!(%coin, "Browsing collection");
+
+        let (wanted, wanted_rx) = watch::channel(1 + self.prefetch);
+        let images = spawn_images(ctx.recoverer()?, start_coin, coin.clone(), wanted_rx);
+        let (metadata, metadata_rx) = self.load_metadata(ctx)?;
+        let mut browser = Browser {
+            title: ctx
+                .profile
+                .as_ref()
+                .map_or_else(|| coin.clone(), |(name, _profile)| name.clone()),
+            output_dir: ctx.output_dir.clone(),
+            expected: expected_manifest(ctx)?,
+            entries: vec![],
+            list: ListState::default(),
+            loading: Loading::Ongoing,
+            metadata,
+            message: None,
+            prefetch: self.prefetch,
+        };
+
+        // Log lines would draw over the interface, so anything worth knowing goes in its status
+        // line until the terminal is restored
+        logging::set_muted(true);
+        let result = match ratatui::try_init() {
+            Ok(mut terminal) => {
+                let result = browser
+                    .run(&mut terminal, images, metadata_rx, &wanted)
+                    .await;
+                ratatui::restore();
+                result
+            }
+            Err(err) => Err(err.into()),
+        };
+        logging::set_muted(false);
+
+        if let Loading::Failed(err) = &browser.loading {
+            warn!(error = %err, "Recovery stopped early");
+        }
+        info!(images = browser.entries.len(), "Recovered while browsing");
+        result
+    }
+
+    /// Reads the metadata already recovered into the output directory, or else starts recovering
+    /// it from the metadata coin
+    fn load_metadata(&self, ctx: &Context) -> Result<(Metadata, Option<mpsc::Receiver<Metadata>>)> {
+        let recovered = read_metadata_dir(&ctx.output_dir.join(METADATA_DIR))?;
+        if !recovered.is_empty() {
+            return Ok((Metadata::Loaded(recovered), None));
+        }
+        let Ok(coin) = ctx.metadata_coin(self.metadata_coin.as_ref()) else {
+            return Ok((Metadata::Unavailable, None));
+        };
+        let coin_id = coin_id_from_string(&coin)?;
+        let recoverer = ctx.recoverer()?;
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let metadata = match recoverer.recover_metadata(&coin_id).await {
+                Ok(documents) => Metadata::Loaded(
+                    documents
+                        .iter()
+                        .enumerate()
+                        .map(|(index, document)| {
+                            let parsed = document
+                                .as_ref()
+                                .map_err(|err| anyhow!("{err}"))
+                                .and_then(|document| NftMetadata::parse(document));
+                            (index + 1, parsed.map_err(|err| format!("{err:#}")))
+                        })
+                        .collect(),
+                ),
+                Err(err) => Metadata::Failed(format!("{err:#}")),
+            };
+            let _ = tx.send(metadata).await;
+        });
+        Ok((Metadata::Recovering, Some(rx)))
+    }
+}
+
+/// An image recovered while browsing, with what's known about whether it's intact
+struct Entry {
+    image: RecoveredImage,
+    filename: String,
+    sha256: [u8; 32],
+    /// The image's dimensions, or why it can't be decoded
+    decoded: Result<(u32, u32), String>,
+}
+
+impl Entry {
+    fn new(image: RecoveredImage, start_coin: &str) -> Self {
+        // Named as recover-collection names it, so exports land in the same place
+        let filename = image_filename(image.filename.as_deref(), || {
+            format!("{}-{start_coin}.png", image.index)
+        });
+        let decoded = image::load_from_memory(&image.data)
+            .map(|decoded| (decoded.width(), decoded.height()))
+            .map_err(|err| err.to_string());
+        Self {
+            sha256: sha256(&image.data),
+            filename,
+            decoded,
+            image,
+        }
+    }
+}
+
+enum Fetched {
+    Image(Box<Entry>),
+    End,
+    Failed(String),
+}
+
+enum Loading {
+    Ongoing,
+    Ended,
+    Failed(String),
+}
+
+/// The name and attributes of an NFT, from its CHIP-0007 metadata
+struct NftMetadata {
+    name: Option<String>,
+    attributes: Vec<(String, String)>,
+}
+
+impl NftMetadata {
+    fn parse(document: &[u8]) -> Result<Self> {
+        let document: Value = serde_json::from_slice(document)?;
+        let text = |value: &Value| value.as_str().map_or(value.to_string(), String::from);
+        let attributes = document["attributes"]
+            .as_array()
+            .map(|attributes| {
+                attributes
+                    .iter()
+                    .map(|attribute| (text(&attribute["trait_type"]), text(&attribute["value"])))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            name: document["name"].as_str().map(String::from),
+            attributes,
+        })
+    }
+}
+
+enum Metadata {
+    /// There's no recovered metadata and no metadata coin to recover it from
+    Unavailable,
+    Recovering,
+    /// The metadata of each NFT by its index, or why it couldn't be read
+    Loaded(BTreeMap<usize, Result<NftMetadata, String>>),
+    Failed(String),
+}
+
+struct Browser {
+    title: String,
+    output_dir: PathBuf,
+    /// The manifest images are checked against, if there is one
+    expected: Option<Manifest>,
+    entries: Vec<Entry>,
+    list: ListState,
+    loading: Loading,
+    metadata: Metadata,
+    /// The result of the last export, shown in the status line
+    message: Option<String>,
+    prefetch: usize,
+}
+
+impl Browser {
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        mut images: mpsc::Receiver<Fetched>,
+        mut metadata: Option<mpsc::Receiver<Metadata>>,
+        wanted: &watch::Sender<usize>,
+    ) -> Result<()> {
+        let mut events = EventStream::new();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                        if !self.key(key).await {
+                            return Ok(());
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(()),
+                },
+                Some(fetched) = images.recv() => self.fetched(fetched),
+                Some(loaded) = async { metadata.as_mut()?.recv().await } => {
+                    self.metadata = loaded;
+                    metadata = None;
+                }
+            }
+            // Keep a few images recovered past the selected one, so scrolling doesn't wait
+            let selected = self.list.selected().unwrap_or(0);
+            wanted.send_if_modified(|wanted| {
+                let ahead = selected + 1 + self.prefetch;
+                let modified = ahead > *wanted;
+                *wanted = (*wanted).max(ahead);
+                modified
+            });
+        }
+    }
+
+    fn fetched(&mut self, fetched: Fetched) {
+        match fetched {
+            Fetched::Image(entry) => {
+                self.entries.push(*entry);
+                if self.list.selected().is_none() {
+                    self.list.select(Some(0));
+                }
+            }
+            Fetched::End => self.loading = Loading::Ended,
+            Fetched::Failed(err) => self.loading = Loading::Failed(err),
+        }
+    }
+
+    /// Handles a key press. Returns false to quit
+    async fn key(&mut self, key: KeyEvent) -> bool {
+        let last = self.entries.len().saturating_sub(1);
+        let selected = self.list.selected().unwrap_or(0);
+        let select = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => selected + 1,
+            KeyCode::PageUp => selected.saturating_sub(PAGE),
+            KeyCode::PageDown => selected + PAGE,
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            KeyCode::Char('e') => {
+                self.message = Some(match self.export().await {
+                    Ok(Some(path)) => format!("Exported {}", path.display()),
+                    Ok(None) => String::from("Nothing to export yet"),
+                    Err(err) => format!("Unable to export: {err:#}"),
+                });
+                return true;
+            }
+            _ => return true,
+        };
+        if !self.entries.is_empty() {
+            self.list.select(Some(select.min(last)));
+        }
+        true
+    }
+
+    /// Writes the selected image to the images directory, as recover-collection would
+    async fn export(&self) -> Result<Option<PathBuf>> {
+        let Some(entry) = self.selected() else {
+            return Ok(None);
+        };
+        let dir = self.output_dir.join(IMAGES_DIR);
+        fs::create_dir_all(&dir).await?;
+        let path = dir.join(&entry.filename);
+        fs::write(&path, &entry.image.data).await?;
+        Ok(Some(path))
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.entries.get(self.list.selected()?)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list, details] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+
+        let mut items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| ListItem::new(format!("{:>4}  {}", entry.image.index, entry.filename)))
+            .collect();
+        if matches!(self.loading, Loading::Ongoing) {
+            items
+                .push(ListItem::new("      recovering...").style(Style::new().fg(Color::DarkGray)));
+        }
+        let items = List::new(items)
+            .block(Block::bordered().title(format!(" {} ", self.title)))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(items, list, &mut self.list);
+
+        let lines = match self.selected() {
+            Some(entry) => self.details(entry),
+            None if matches!(self.loading, Loading::Ongoing) => {
+                vec![Line::from("Recovering the first image...")]
+            }
+            None => vec![Line::from("No images were recovered")],
+        };
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Image "))
+                .wrap(Wrap { trim: false }),
+            details,
+        );
+
+        frame.render_widget(Paragraph::new(self.status()), status);
+    }
+
+    fn details(&self, entry: &Entry) -> Vec<Line<'static>> {
+        fn field(label: &str, value: impl Into<Span<'static>>) -> Line<'static> {
+            Line::from(vec![
+                Span::styled(
+                    format!("{label:<12}"),
+                    Style::new().add_modifier(Modifier::BOLD),
+                ),
+                value.into(),
+            ])
+        }
+        let good = Style::new().fg(Color::Green);
+        let bad = Style::new().fg(Color::Red);
+        let unknown = Style::new().fg(Color::Yellow);
+        let bytes = entry.image.data.len();
+
+        let mut lines = vec![
+            field("Index", entry.image.index.to_string()),
+            field("Filename", entry.filename.clone()),
+            field("Start coin", entry.image.start_coin.to_string()),
+            field("End coin", entry.image.end_coin.to_string()),
+            field(
+                "Size",
+                format!("{} ({bytes} bytes)", HumanBytes(bytes as u64)),
+            ),
+            field("SHA-256", hex::encode(entry.sha256)),
+            Line::default(),
+        ];
+
+        lines.push(match &entry.decoded {
+            Ok((width, height)) => {
+                field("Decodes", Span::styled(format!("{width}x{height}"), good))
+            }
+            Err(err) => field("Decodes", Span::styled(format!("no, {err}"), bad)),
+        });
+        let manifest = match &self.expected {
+            None => Span::styled("no manifest to check against", unknown),
+            Some(expected) => match expected
+                .files
+                .get(&format!("{IMAGES_DIR}/{}", entry.filename))
+            {
+                Some(hash) if *hash == entry.sha256 => Span::styled("matches", good),
+                Some(_) => Span::styled("differs", bad),
+                None => Span::styled("not listed", unknown),
+            },
+        };
+        lines.push(field("Manifest", manifest));
+        lines.push(Line::default());
+
+        match &self.metadata {
+            Metadata::Unavailable => lines.push(field(
+                "Metadata",
+                String::from("none recovered, and no metadata coin"),
+            )),
+            Metadata::Recovering => lines.push(field("Metadata", String::from("recovering..."))),
+            Metadata::Failed(err) => lines.push(field("Metadata", format!("failed, {err}"))),
+            Metadata::Loaded(metadata) => match metadata.get(&entry.image.index) {
+                None => lines.push(field("Metadata", String::from("none for this index"))),
+                Some(Err(err)) => lines.push(field("Metadata", format!("unreadable, {err}"))),
+                Some(Ok(metadata)) => {
+                    lines.push(field(
+                        "Name",
+                        metadata.name.clone().unwrap_or(String::from("(none)")),
+                    ));
+                    lines.extend(
+                        metadata
+                            .attributes
+                            .iter()
+                            .map(|(trait_type, value)| field(trait_type, value.clone())),
+                    );
+                }
+            },
+        }
+        lines
+    }
+
+    fn status(&self) -> Line<'static> {
+        let loading = match &self.loading {
+            Loading::Ongoing => String::from("recovering"),
+            Loading::Ended => String::from("no more images"),
+            Loading::Failed(err) => format!("stopped, {err}"),
+        };
+        let message = self
+            .message
+            .as_ref()
+            .map_or(String::new(), |message| format!("  |  {message}"));
+        Line::from(format!(
+            " {} images, {loading}{message}  |  up/down j/k pgup/pgdn home/end  e export  q quit",
+            self.entries.len()
+        ))
+        .style(Style::new().add_modifier(Modifier::REVERSED))
+    }
+}
+
+/// Recovers the collection in the background, only as far as the number of images `wanted`
+fn spawn_images(
+    recoverer: Recoverer,
+    start_coin: Bytes32,
+    coin: String,
+    mut wanted: watch::Receiver<usize>,
+) -> mpsc::Receiver<Fetched> {
+    let (tx, rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        let mut images = pin!(recoverer.recover_collection(&start_coin));
+        let mut loaded = 0;
+        loop {
+            // Stops once the browser has quit and dropped the sender
+            if wanted.wait_for(|wanted| *wanted > loaded).await.is_err() {
+                return;
+            }
+            let fetched = match images.next().await {
+                Some(Ok(image)) => {
+                    loaded += 1;
+                    Fetched::Image(Box::new(Entry::new(image, &coin)))
+                }
+                Some(Err(err)) => Fetched::Failed(format!("{err:#}")),
+                None => Fetched::End,
+            };
+            let finished = !matches!(fetched, Fetched::Image(_));
+            if tx.send(fetched).await.is_err() || finished {
+                return;
+            }
+        }
+    });
+    rx
+}
+
+/// The manifest in the output directory, or else the known collection's
+fn expected_manifest(ctx: &Context) -> Result<Option<Manifest>> {
+    let file = ctx.output_dir.join(MANIFEST_FILE);
+    if file.is_file() {
+        return Manifest::parse(&std::fs::read_to_string(file)?).map(Some);
+    }
+    match ctx
+        .profile
+        .as_ref()
+        .and_then(|(name, _profile)| find_known(name))
+    {
+        Some(known) => known.expected_manifest(),
+        None => Ok(None),
+    }
+}
+
+/// Reads the `metadata_NNN.json` files recover-metadata writes, by index
+fn read_metadata_dir(dir: &Path) -> Result<BTreeMap<usize, Result<NftMetadata, String>>> {
+    let mut metadata = BTreeMap::new();
+    for path in list_files(dir)? {
+        let index = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("metadata_"))
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|index| index.parse().ok());
+        if let Some(index) = index {
+            let parsed = NftMetadata::parse(&std::fs::read(&path)?);
+            metadata.insert(index, parsed.map_err(|err| format!("{err:#}")));
+        }
+    }
+    Ok(metadata)
+}
@@ -0,0 +1,131 @@
+use crate::commands::recover_collection::write_collection;
+use crate::config::Context;
+use crate::logging::{output, RecoveryProgress};
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::archive::{list_files, IMAGES_DIR, METADATA_DIR};
+use recovery_tools::{png, sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
+use tracing::info;
+
+#[derive(Args)]
+#[command(about = "Compares recovered files against a copy obtained elsewhere")]
+pub struct Compare {
+    /// The directory containing the recovered output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    recovered: Option<PathBuf>,
+
+    /// The directory of images and metadata obtained elsewhere
+    #[arg(long)]
+    other: PathBuf,
+
+    /// Re-recover the collection starting at this coin into the recovered directory first
+    #[arg(short, long)]
+    coin: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FileStatus {
+    Identical,
+    /// Bytes differ, but only in PNG ancillary chunks or how the image data is split
+    SameImage,
+    Different,
+    Missing,
+    Extra,
+}
+
+impl FileStatus {
+    fn label(self) -> &'static str {
+        match self {
+            FileStatus::Identical => "identical",
+            FileStatus::SameImage => "same image",
+            FileStatus::Different => "different",
+            FileStatus::Missing => "missing",
+            FileStatus::Extra => "extra",
+        }
+    }
+}
+
+impl Compare {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let recovered_dir = ctx.dir_or(self.recovered.as_ref());
+        if let Some(coin) = &self.coin {
+            info!(%coin, "Recovering collection");
+            let progress = Arc::new(RecoveryProgress::new(None));
+            let recoverer = ctx.recoverer()?.with_observer(progress.clone());
+            let outputdir = recovered_dir.join(IMAGES_DIR);
+            fs::create_dir_all(&outputdir).await?;
+            write_collection(&recoverer, coin, &outputdir).await?;
+            progress.finish();
+        }
+
+        info!(
+            recovered = %recovered_dir.display(),
+            other = %self.other.display(),
+            "Comparing files"
+        );
+        let recovered = collect_files(recovered_dir, false)?;
+        let other = collect_files(&self.other, true)?;
+
+        let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+        for (name, recovered_path) in &recovered {
+            let status = match other.get(name) {
+                Some(other_path) => compare_files(recovered_path, other_path).await?,
+                None => FileStatus::Missing,
+            };
+            report(&mut counts, status, name);
+        }
+        for name in other.keys() {
+            if !recovered.contains_key(name) {
+                report(&mut counts, FileStatus::Extra, name);
+            }
+        }
+
+        for (label, count) in &counts {
+            info!(count, "Total {label}");
+        }
+
+        anyhow::Ok(())
+    }
+}
+
+fn report(counts: &mut BTreeMap<&'static str, usize>, status: FileStatus, name: &str) {
+    *counts.entry(status.label()).or_default() += 1;
+    output(format!("{name}: {}", status.label()));
+}
+
+/// Collects files by name from the images and metadata directories, and optionally the top of `dir`
+fn collect_files(dir: &Path, include_top_level: bool) -> Result<BTreeMap<String, PathBuf>> {
+    let mut paths = list_files(&dir.join(IMAGES_DIR))?;
+    paths.extend(list_files(&dir.join(METADATA_DIR))?);
+    if include_top_level {
+        paths.extend(list_files(dir)?);
+    }
+
+    let mut files = BTreeMap::new();
+    for path in paths {
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            files.entry(name.to_string()).or_insert(path);
+        }
+    }
+    Ok(files)
+}
+
+async fn compare_files(recovered: &Path, other: &Path) -> Result<FileStatus> {
+    let recovered = fs::read(recovered).await?;
+    let other = fs::read(other).await?;
+
+    if sha256(&recovered) == sha256(&other) {
+        return Ok(FileStatus::Identical);
+    }
+    match (
+        png::critical_digest(&recovered),
+        png::critical_digest(&other),
+    ) {
+        (Some(recovered), Some(other)) if recovered == other => Ok(FileStatus::SameImage),
+        _ => Ok(FileStatus::Different),
+    }
+}
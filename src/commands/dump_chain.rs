@@ -0,0 +1,64 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use futures::StreamExt;
+use recovery_tools::assemble::{ChainRecord, CollectionAssembler};
+use recovery_tools::coin_id_from_string;
+use std::path::PathBuf;
+use std::pin::pin;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+#[derive(Args)]
+#[command(
+    about = "Dumps the raw memos and conditions of a collection's data chain to a JSONL file"
+)]
+pub struct DumpChain {
+    /// The coin ID at the start of the collection [default: the profile's start_coin]
+    #[arg(short, long)]
+    coin: Option<String>,
+
+    /// The JSONL file to write, one line per coin
+    #[arg(short, long, default_value = "chain-dump.jsonl")]
+    output: PathBuf,
+}
+
+impl DumpChain {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let coin = ctx.start_coin(self.coin.as_ref())?;
+        info!(%coin, output = %self.output.display(), "Dumping data chain");
+        let recoverer = ctx.recoverer()?;
+        let start_coin = coin_id_from_string(&coin)?;
+        let mut dump = File::create(&self.output).await?;
+
+        // The memos are assembled as they are dumped, only to know where the collection ends
+        let mut assembler = CollectionAssembler::new().with_framing(ctx.framing.clone());
+        let mut coins = 0;
+        let mut links = pin!(recoverer.walk_chain(&start_coin));
+        while let Some(link) = links.next().await {
+            let link = link?;
+            let mut line = serde_json::to_string(&ChainRecord::from_link(&link, &ctx.framing)?)?;
+            line.push('\n');
+            dump.write_all(line.as_bytes()).await?;
+            coins += 1;
+
+            let assembled = link.require_memo().and_then(|memo| assembler.push(memo));
+            if let Err(err) = assembled {
+                warn!(coin = %link.coin.coin.name(), error = %err, "Stopping at a coin that can't be assembled");
+                break;
+            }
+            if assembler.is_finished() {
+                break;
+            }
+        }
+        dump.flush().await?;
+
+        if !assembler.is_finished() {
+            warn!("The dump does not reach the end of the collection");
+        }
+        output(format!("wrote {} coins={coins}", self.output.display()));
+        anyhow::Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::archive::{list_files, IMAGES_DIR, METADATA_DIR};
+use recovery_tools::chia::nft::trace_nft;
+use recovery_tools::ipfs::{
+    cid_from_uri, Cid, DagBuilder, DagNode, DEFAULT_CHUNK_SIZE, DEFAULT_MAX_LINKS,
+};
+use recovery_tools::launcher_id_from_string;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+#[derive(Args)]
+#[command(about = "Exports a recovered collection as an IPFS CAR file and prints its CIDs")]
+pub struct ExportCar {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The CAR file to write
+    #[arg(short, long, default_value = "collection.car")]
+    output: PathBuf,
+
+    /// Size of the chunks files are split into, in bytes
+    #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: usize,
+
+    /// Maximum number of links in each file DAG node
+    #[arg(long, default_value_t = DEFAULT_MAX_LINKS)]
+    max_links: usize,
+
+    /// NFT ID whose on-chain data and metadata URIs are checked against the computed CIDs.
+    /// Can be passed multiple times
+    #[arg(short, long)]
+    nft_id: Vec<String>,
+}
+
+/// The computed CIDs for every file and directory in the export
+struct ExportedCids {
+    root: Cid,
+    images_dir: Cid,
+    metadata_dir: Cid,
+    images: HashMap<String, Cid>,
+    metadata: HashMap<String, Cid>,
+}
+
+impl ExportedCids {
+    /// Finds the file the URI's CID and path point at in this export
+    fn resolve(&self, cid: &Cid, path: &str) -> Option<String> {
+        let path = path.trim_end_matches('/');
+        let lookup = |files: &HashMap<String, Cid>, dir: &str, name: &str| {
+            files.contains_key(name).then(|| format!("{dir}/{name}"))
+        };
+
+        if path.is_empty() {
+            return self
+                .images
+                .iter()
+                .map(|(name, file_cid)| (IMAGES_DIR, name, file_cid))
+                .chain(
+                    self.metadata
+                        .iter()
+                        .map(|(name, file_cid)| (METADATA_DIR, name, file_cid)),
+                )
+                .find(|(_dir, _name, file_cid)| *file_cid == cid)
+                .map(|(dir, name, _file_cid)| format!("{dir}/{name}"));
+        }
+        if *cid == self.images_dir {
+            return lookup(&self.images, IMAGES_DIR, path);
+        }
+        if *cid == self.metadata_dir {
+            return lookup(&self.metadata, METADATA_DIR, path);
+        }
+        if *cid == self.root {
+            let (dir, name) = path.split_once('/')?;
+            return match dir {
+                "images" => lookup(&self.images, IMAGES_DIR, name),
+                "metadata" => lookup(&self.metadata, METADATA_DIR, name),
+                _ => None,
+            };
+        }
+        None
+    }
+}
+
+impl ExportCar {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        info!(
+            dir = %dir.display(),
+            output = %self.output.display(),
+            "Exporting collection"
+        );
+        let mut builder = DagBuilder::new(self.chunk_size, self.max_links);
+
+        let (images_dir, images) =
+            add_directory(&mut builder, &dir.join(IMAGES_DIR), "images").await?;
+        let (metadata_dir, metadata) =
+            add_directory(&mut builder, &dir.join(METADATA_DIR), "metadata").await?;
+        let root = builder.add_directory(&[
+            (String::from("images"), images_dir.clone()),
+            (String::from("metadata"), metadata_dir.clone()),
+        ]);
+
+        // The same lines as `ipfs add --wrap-with-directory`, with the root's name left empty
+        output(format!("added {} images", images_dir.cid));
+        output(format!("added {} metadata", metadata_dir.cid));
+        output(format!("added {} ", root.cid));
+
+        fs::write(&self.output, builder.to_car(&root.cid)).await?;
+        output(format!("wrote {}", self.output.display()));
+
+        if self.nft_id.is_empty() {
+            return anyhow::Ok(());
+        }
+
+        let cids = ExportedCids {
+            root: root.cid,
+            images_dir: images_dir.cid,
+            metadata_dir: metadata_dir.cid,
+            images,
+            metadata,
+        };
+        self.check_nft_uris(ctx, &cids).await
+    }
+
+    async fn check_nft_uris(&self, ctx: &Context, cids: &ExportedCids) -> Result<()> {
+        let client = ctx.client()?;
+        let mut mismatches = 0;
+
+        for nft_id in &self.nft_id {
+            let launcher_id = launcher_id_from_string(nft_id)?;
+            let ownership = trace_nft(&client, &launcher_id).await?;
+            let Some(metadata) = ownership.metadata else {
+                warn!(%nft_id, "On-chain metadata is not in the standard NFT format");
+                continue;
+            };
+
+            for uri in metadata.data_uris.iter().chain(&metadata.metadata_uris) {
+                let Some((cid, path)) = cid_from_uri(uri) else {
+                    debug!(%nft_id, %uri, "Skipping non-IPFS URI");
+                    continue;
+                };
+                if let Some(file) = cids.resolve(&cid, &path) {
+                    output(format!("match {nft_id} {uri} {file}"));
+                } else {
+                    mismatches += 1;
+                    output(format!("mismatch {nft_id} {uri}"));
+                }
+            }
+        }
+
+        if mismatches > 0 {
+            warn!(
+                mismatches,
+                "Some on-chain URIs don't match the computed CIDs"
+            );
+        } else {
+            info!("All on-chain IPFS URIs match the computed CIDs");
+        }
+        anyhow::Ok(())
+    }
+}
+
+/// Adds every file in `dir` to the DAG and returns the directory node and each file's CID
+async fn add_directory(
+    builder: &mut DagBuilder,
+    dir: &Path,
+    label: &str,
+) -> Result<(DagNode, HashMap<String, Cid>)> {
+    let mut entries: Vec<(String, DagNode)> = vec![];
+    for path in list_files(dir)? {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let node = builder.add_file(&fs::read(&path).await?);
+        output(format!("added {} {label}/{name}", node.cid));
+        entries.push((name.to_string(), node));
+    }
+
+    let files = entries
+        .iter()
+        .map(|(name, node)| (name.clone(), node.cid.clone()))
+        .collect();
+    Ok((builder.add_directory(&entries), files))
+}
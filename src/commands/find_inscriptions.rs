@@ -0,0 +1,138 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::coin_record::CoinRecord;
+use recovery_tools::chia::coins::conditions_for_coin;
+use recovery_tools::chia::memo::{parse_memos_from_conditions, MemoStrategy};
+use recovery_tools::{find_markers, puzzle_hash_from_string, Marker};
+use std::fmt;
+use tracing::info;
+
+#[derive(Args)]
+#[command(about = "Finds inscriptions created by an address or puzzle hash")]
+pub struct FindInscriptions {
+    /// The address (xch1...) or puzzle hash that created the inscriptions
+    #[arg(short, long)]
+    address: String,
+}
+
+/// What the spend of a coin carries in its memo
+enum SpendClass {
+    Unspent,
+    Unreadable(String),
+    NoMemo,
+    Hint,
+    Data(usize),
+    Markers(Vec<Marker>),
+}
+
+impl SpendClass {
+    fn has(&self, marker: Marker) -> bool {
+        matches!(self, SpendClass::Markers(markers) if markers.contains(&marker))
+    }
+}
+
+impl fmt::Display for SpendClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendClass::Unspent => write!(f, "unspent"),
+            SpendClass::Unreadable(err) => write!(f, "unable to run spend: {err}"),
+            SpendClass::NoMemo => write!(f, "no memo"),
+            SpendClass::Hint => write!(f, "hint"),
+            SpendClass::Data(len) => write!(f, "data ({len} bytes)"),
+            SpendClass::Markers(markers) => {
+                let names: Vec<&str> = markers.iter().map(|marker| marker.name()).collect();
+                write!(f, "{}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl FindInscriptions {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        info!(address = %self.address, "Finding inscriptions");
+        let client = ctx.client()?;
+
+        let puzzle_hash = puzzle_hash_from_string(&self.address)?;
+        let mut coin_records = client
+            .get_coin_records_by_puzzle_hash(&puzzle_hash, Some(true), None, None)
+            .await?;
+        coin_records.sort_by_key(|record| record.confirmed_block_index);
+        info!(coins = coin_records.len(), %puzzle_hash, "Found coins");
+
+        let mut collection_starts = vec![];
+        let mut metadata_coins = vec![];
+        let mut image_starts = vec![];
+
+        for coin_record in &coin_records {
+            let classification = classify_spend(&client, coin_record, ctx.memo_strategy).await;
+            info!(
+                coin = %coin_record.coin.name(),
+                confirmed_height = coin_record.confirmed_block_index,
+                spent_height = coin_record.spent_block_index,
+                %classification,
+                "Classified spend"
+            );
+
+            if classification.has(Marker::CollectionStart) {
+                collection_starts.push(coin_record.coin.name());
+            } else if classification.has(Marker::PngStart) {
+                image_starts.push(coin_record.coin.name());
+            }
+            if classification.has(Marker::MetaStart) {
+                metadata_coins.push(coin_record.coin.name());
+            }
+        }
+
+        // Each kind of coin is named for the command it is used with
+        for coin in &collection_starts {
+            output(format!("recover-collection {coin}"));
+        }
+        for coin in &metadata_coins {
+            output(format!("recover-metadata {coin}"));
+        }
+        for coin in &image_starts {
+            output(format!("recover-image {coin}"));
+        }
+        info!(
+            collections = collection_starts.len(),
+            metadata = metadata_coins.len(),
+            images = image_starts.len(),
+            "Finished finding inscriptions"
+        );
+
+        anyhow::Ok(())
+    }
+}
+
+/// Classifies the memo of a coin's spend using the markers from the library
+async fn classify_spend(
+    client: &FullnodeClient,
+    coin_record: &CoinRecord,
+    memo_strategy: MemoStrategy,
+) -> SpendClass {
+    if coin_record.spent_block_index == 0 {
+        return SpendClass::Unspent;
+    }
+
+    let memo = conditions_for_coin(client, coin_record)
+        .await
+        .and_then(|conditions| parse_memos_from_conditions(conditions, memo_strategy));
+    let memo = match memo {
+        Ok(Some(memo)) => memo,
+        Ok(None) => return SpendClass::NoMemo,
+        Err(err) => return SpendClass::Unreadable(err.to_string()),
+    };
+
+    let markers = find_markers(&memo);
+    if !markers.is_empty() {
+        return SpendClass::Markers(markers.iter().map(|found| found.marker).collect());
+    }
+    if memo.len() == 32 {
+        return SpendClass::Hint;
+    }
+    SpendClass::Data(memo.len())
+}
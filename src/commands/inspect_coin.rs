@@ -0,0 +1,148 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use chia::clvm_traits::ToClvm;
+use chia::protocol::{Bytes, Program};
+use chia::traits::Streamable;
+use chia_wallet_sdk::Condition;
+use clap::Args;
+use clvmr::allocator::SExp;
+use clvmr::Allocator;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use recovery_tools::chia::clvm::{condition_name, disassemble_data, disassemble_program};
+use recovery_tools::chia::coins::conditions_for_puzz_solution;
+use recovery_tools::chia::memo::parse_memos_from_conditions;
+use recovery_tools::{coin_id_from_string, find_markers};
+use tracing::info;
+
+/// Bytes of each memo shown in the hex and UTF-8 previews, unless --full is passed
+const PREVIEW_BYTES: usize = 48;
+
+#[derive(Args)]
+#[command(about = "Shows everything about a coin's spend that recovery relies on")]
+pub struct InspectCoin {
+    /// The coin ID to inspect
+    #[arg(short, long)]
+    coin: String,
+
+    /// Print each memo in full, instead of a preview of its first bytes
+    #[arg(long)]
+    full: bool,
+}
+
+impl InspectCoin {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let client = ctx.client()?;
+        let coin_id = coin_id_from_string(&self.coin)?;
+        let record = client
+            .get_coin_record_by_name(&coin_id)
+            .await?
+            .ok_or(anyhow!("No Coin Record found."))?;
+        output("== Coin record");
+        output(format!("coin: {}", record.coin.name()));
+        output(format!("parent: {}", record.coin.parent_coin_info));
+        output(format!("puzzle_hash: {}", record.coin.puzzle_hash));
+        output(format!("amount: {}", record.coin.amount));
+        output(format!(
+            "confirmed_height: {}",
+            record.confirmed_block_index
+        ));
+        output(format!("spent_height: {}", record.spent_block_index));
+        output(format!("coinbase: {}", record.coinbase));
+        output(format!("timestamp: {}", record.timestamp));
+        if record.spent_block_index == 0 {
+            info!("Coin is unspent, so there is no spend to inspect");
+            return anyhow::Ok(());
+        }
+
+        let spend = client
+            .get_puzzle_and_solution(&coin_id, record.spent_block_index)
+            .await?;
+        let puzzle = Program::from_bytes(&spend.puzzle_reveal.to_bytes())?;
+        let solution = Program::from_bytes(&spend.solution.to_bytes())?;
+        section("Puzzle reveal");
+        output(disassemble_program(&puzzle)?);
+        section("Solution");
+        output(disassemble_program(&solution)?);
+
+        let conditions = conditions_for_puzz_solution(&solution, &puzzle)?;
+        section("Conditions");
+        print_conditions(&conditions)?;
+        section("Memos");
+        self.print_memos(&conditions);
+
+        let memo_strategy = ctx.memo_strategy;
+        section(&format!("Data payload (memo strategy {memo_strategy})"));
+        match parse_memos_from_conditions(conditions, memo_strategy)? {
+            Some(payload) => self.print_bytes(&payload, "payload"),
+            None => output("none"),
+        }
+
+        anyhow::Ok(())
+    }
+
+    fn print_memos(&self, conditions: &[Condition<Program>]) {
+        let create_coins = conditions
+            .iter()
+            .filter_map(|condition| condition.clone().into_create_coin());
+        let mut memos = 0;
+        for (output, create_coin) in create_coins.enumerate() {
+            for (index, memo) in create_coin.memos.iter().enumerate() {
+                self.print_bytes(memo, &format!("output {output} memo {index}"));
+                memos += 1;
+            }
+        }
+        if memos == 0 {
+            output("none");
+        }
+    }
+
+    /// Prints the length, previews, and markers of a memo or payload
+    fn print_bytes(&self, data: &Bytes, label: &str) {
+        let shown = if self.full {
+            &data[..]
+        } else {
+            &data[..data.len().min(PREVIEW_BYTES)]
+        };
+        let ellipsis = if shown.len() < data.len() { "..." } else { "" };
+        let utf8: String = String::from_utf8_lossy(shown)
+            .chars()
+            .map(|c| if c.is_control() { '.' } else { c })
+            .collect();
+        let markers: Vec<String> = find_markers(data)
+            .iter()
+            .map(|found| format!("{}@{}", found.marker.name(), found.offset))
+            .collect();
+        let markers = if markers.is_empty() {
+            String::from("none")
+        } else {
+            markers.join(", ")
+        };
+        output(format!("{label}: length={} markers={markers}", data.len()));
+        output(format!("  hex: {}{ellipsis}", hex::encode(shown)));
+        output(format!("  utf8: {utf8}{ellipsis}"));
+    }
+}
+
+/// Starts a section of the report after the first, set off by a blank line
+fn section(title: &str) {
+    output("");
+    output(format!("== {title}"));
+}
+
+fn print_conditions(conditions: &[Condition<Program>]) -> Result<()> {
+    let mut allocator = Allocator::new();
+    for (index, condition) in conditions.iter().enumerate() {
+        let node = condition.to_clvm(&mut allocator)?;
+        let SExp::Pair(opcode, args) = allocator.sexp(node) else {
+            continue;
+        };
+        let opcode = allocator.small_number(opcode).unwrap_or_default();
+        let name = condition_name(opcode).map_or_else(|| opcode.to_string(), String::from);
+        output(format!(
+            "{index} {name} {}",
+            disassemble_data(&allocator, args)
+        ));
+    }
+    anyhow::Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use recovery_tools::known::KNOWN_COLLECTIONS;
+
+#[derive(Args)]
+#[command(about = "Lists the collections known to be inscribed on chain")]
+pub struct Known {
+    #[command(subcommand)]
+    command: KnownCommand,
+}
+
+#[derive(Subcommand)]
+enum KnownCommand {
+    /// Lists every known collection
+    List,
+}
+
+impl Known {
+    pub async fn execute(&self, _ctx: &Context) -> Result<()> {
+        match self.command {
+            KnownCommand::List => {
+                for known in KNOWN_COLLECTIONS {
+                    output(format!(
+                        "{} ({}) start_coin={} metadata_coin={} images={} manifest={}",
+                        known.name,
+                        known.title,
+                        known.start_coin,
+                        known.metadata_coin.unwrap_or("unknown"),
+                        known
+                            .images
+                            .map_or_else(|| String::from("unknown"), |images| images.to_string()),
+                        if known.manifest.is_some() {
+                            "yes"
+                        } else {
+                            "no"
+                        },
+                    ));
+                }
+            }
+        }
+        anyhow::Ok(())
+    }
+}
@@ -1,17 +1,15 @@
-use std::time::Duration;
-use crate::chia::client::get_chia_client;
-use crate::chia::coins::conditions_for_coin;
-use crate::chia::memo::parse_memos_from_conditions;
-use anyhow::{anyhow, Result};
-use chia::sha2::Sha256;
-use chia_wallet_sdk::Condition;
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
 use clap::Args;
-use dg_xch_clients::api::full_node::FullnodeAPI;
-use dg_xch_clients::rpc::full_node::FullnodeClient;
-use dg_xch_core::blockchain::coin_record::CoinRecord;
-use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
-use tokio::time::sleep;
-use recovery_tools::{is_collection_end, is_collection_start, is_meta, is_png_start};
+use recovery_tools::chia::locate::GapMemo;
+use recovery_tools::chia::RecoveryObserver;
+use recovery_tools::launcher_id_from_string;
+use recovery_tools::payload::{decode_payload, PayloadKind};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info};
 
 #[derive(Args)]
 #[command(about = "Finds key coins starting from an NFT in the collection")]
@@ -22,168 +20,93 @@ pub struct LocateNFTData {
 }
 
 impl LocateNFTData {
-    pub async fn execute(&self, port: u16) -> Result<()> {
-        println!("Locating NFT data for: {}", self.nft_id);
-        let client = get_chia_client(port);
-
-        let mut current_coin = get_nft_parent_in_direct_chain(&client, &self.nft_id).await?;
-        println!(
-            "NFT Parent with parent traceability: {}",
-            current_coin.coin.name()
-        );
-
-        let mut found_gap = false; // 🏴‍☠️ 💰 🗺️ 💎 🏆 🎁 📜
-        let mut found_meta = false;
-        let mut found_collection_end = false;
-
-        while current_coin.spent_block_index > 0 {
-            // First, we don't care about ephemeral coins
-            if current_coin.spent_block_index == current_coin.confirmed_block_index {
-                current_coin = advance_parent(&client, &current_coin).await?;
-                continue;
-            }
-            // Keep going until we find the first non-hint memo
-            // First memo is a gap between meta and minting
-            // Then meta coin
-            // Then the "End Collection" image
-            // <image data in between>
-            // Then the "Start Collection" image
-
-            let conditions = conditions_for_coin(&client, &current_coin).await?;
-            let memo_opt = parse_memos_from_conditions(conditions);
-            if memo_opt.is_none() {
-                if found_gap {
-                    // If we found the gap and have no memo before the collection is done, that is not expected
-                    anyhow::bail!("Unexpected spend with no memo in the collection data")
-                }
-                current_coin = advance_parent(&client, &current_coin).await?;
-                continue;
-            }
-            let memo = memo_opt.unwrap();
-
-            if !found_gap {
-                if memo.len() != 32 {
-                    found_gap = true;
-                    println!("Found a spend before the NFT mints with a memo that isn't the metadata, but something seems to be here 🏴‍☠️ 💰 🗺️ 💎 🏆 🎁 📜:");
-                    let base64_str = str::from_utf8(&memo)?;
-                    println!("{base64_str}");
-                    sleep(Duration::from_secs(5)).await;
-                }
-                current_coin = advance_parent(&client, &current_coin).await?;
-                continue;
-            }
-
-            if !found_meta {
-                if !is_meta(&memo) {
-                    println!("{memo}");
-                    anyhow::bail!("Did not find the metadata at the expected location");
-                }
-                found_meta = true;
-                println!("Found metadata at coin: {}", current_coin.coin.name());
-                current_coin = advance_parent(&client, &current_coin).await?;
-                continue;
-            }
-            // Was checked for none earlier
-            if !found_collection_end {
-                if !is_collection_end(&memo) {
-                    anyhow::bail!(
-                        "Did not find the end of the collection at the expected location"
-                    );
-                }
-                found_collection_end = true;
-                println!("End of collection at coin: {}", current_coin.coin.name());
-                current_coin = advance_parent(&client, &current_coin).await?;
-                continue;
-            }
-
-            if is_png_start(&memo) {
-                println!("Found an image coin at: {}", current_coin.coin.name());
-            }
-
-            if is_collection_start(&memo) {
-                println!("Found collection start at: {}", current_coin.coin.name());
-                return anyhow::Ok(());
-            }
-
-            current_coin = advance_parent(&client, &current_coin).await?;
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        info!(nft_id = %self.nft_id, "Locating NFT data");
+        let recoverer = ctx.recoverer()?.with_observer(Arc::new(GapSaver {
+            dir: ctx.output_dir.clone(),
+        }));
+
+        let launcher_id = launcher_id_from_string(&self.nft_id)?;
+        let located = recoverer.locate(&launcher_id).await?;
+        output(format!("nft_parent {}", located.nft_parent));
+        if let Some(coin) = &located.metadata_coin {
+            output(format!("metadata_coin {coin}"));
+        }
+        if let Some(coin) = &located.collection_end {
+            output(format!("collection_end {coin}"));
+        }
+        for coin in &located.image_coins {
+            output(format!("image_coin {coin}"));
+        }
+        if let Some(coin) = &located.collection_start {
+            output(format!("collection_start {coin}"));
         }
 
         anyhow::Ok(())
     }
 }
 
-/// Finds the parent coin of the NFT that is in the direct lineage back to the metadata/image coins
-/// Once this coin is found, all that needs to happen is looking at parent_coin_id all the way up
-async fn get_nft_parent_in_direct_chain(
-    client: &FullnodeClient,
-    nft_id: &str,
-) -> Result<CoinRecord> {
-    let (_hrp, launcher_id) = bech32::decode(nft_id)?;
-    let coinid = Bytes32::new(&launcher_id[..]);
-    let launcher_coin = client
-        .get_coin_record_by_name(&coinid)
-        .await?
-        .ok_or(anyhow!("Launcher Coin Record not found."))?;
-
-    let eph_coin = client
-        .get_coin_record_by_name(&launcher_coin.coin.parent_coin_info)
-        .await?
-        .ok_or(anyhow!("Ephemeral Coin Record not found."))?;
-
-    let direct_parent = client
-        .get_coin_record_by_name(&eph_coin.coin.parent_coin_info)
-        .await?
-        .ok_or(anyhow!("Parent of ephemeral not found"))?;
-
-    // Now, the conditions on the spend of the direct parent should enable us to find the other parent of the ephemeral coin
-    // linked by CREATE_COIN_ANNOUNCEMENT/ASSERT_COIN_ANNOUNCEMENT
-    let conditions = conditions_for_coin(client, &direct_parent).await?;
-
-    // Now, we need to find the CREATE_COIN_ANNOUNCEMENT
-    let create_coin_announcements = conditions
-        .into_iter()
-        .filter_map(Condition::into_create_coin_announcement);
-
-    let mut input_coins: Vec<CoinRecord> = vec![];
-
-    for announcement in create_coin_announcements {
-        let mut hasher = Sha256::new();
-        hasher.update(eph_coin.coin.parent_coin_info);
-        hasher.update(&announcement.message);
-        let message = hasher.finalize().to_vec();
+/// Saves the gap memo as soon as it is found, so it isn't lost if the collection data can't be
+/// located
+struct GapSaver {
+    dir: PathBuf,
+}
 
-        // now we have to find coins with ASSERT_COIN_ANNOUNCEMENT of `message`
-        // Get all the coin creations in the block, and find the matching assert
-        let block = client
-            .get_block_record_by_height(eph_coin.confirmed_block_index)
-            .await?;
-        let (_additions, removals) = client
-            .get_additions_and_removals(&block.header_hash)
-            .await?;
-        for removal in removals {
-            let conditions = conditions_for_coin(client, &removal).await?;
-            let assert_coin_announcements = conditions
-                .into_iter()
-                .filter_map(Condition::into_assert_coin_announcement);
-            for assert_coin_announcement in assert_coin_announcements {
-                if assert_coin_announcement.announcement_id[..] == message {
-                    input_coins.push(removal.clone());
-                }
-            }
+impl RecoveryObserver for GapSaver {
+    fn gap_found(&self, gap: &GapMemo) {
+        if let Err(err) = save_gap_memo(gap, &self.dir) {
+            error!(coin = %gap.coin_id, error = %err, "Failed to save the gap memo");
         }
     }
+}
 
-    if input_coins.len() != 1 {
-        anyhow::bail!("Unexpected number of input coins found");
+/// Writes the memo found between the metadata and the mint to `output-gap`, along with its
+/// decoded form, and logs a short report about what was found
+fn save_gap_memo(gap: &GapMemo, dir: &Path) -> Result<()> {
+    let outputdir = dir.join("output-gap");
+    fs::create_dir_all(&outputdir)?;
+
+    let coin_name = hex::encode(gap.coin_id);
+    info!(
+        coin = %gap.coin_id,
+        memo_length = gap.memo.len(),
+        "Found a spend before the NFT mints with a memo that isn't the metadata"
+    );
+
+    let raw_file = outputdir.join(format!("{coin_name}.memo"));
+    fs::write(&raw_file, gap.memo.as_ref())?;
+    info!(file = %raw_file.display(), "Wrote raw memo");
+
+    let decoded = decode_payload(&gap.memo);
+    let encodings = if decoded.encodings.is_empty() {
+        String::from("none")
+    } else {
+        decoded.encodings.join(" -> ")
+    };
+    info!(
+        %encodings,
+        content = %decoded.kind,
+        bytes = decoded.data.len(),
+        "Decoded memo"
+    );
+
+    let decoded_file = outputdir.join(format!("{coin_name}-decoded.{}", decoded.kind.extension()));
+    fs::write(&decoded_file, &decoded.data)?;
+    info!(file = %decoded_file.display(), "Wrote decoded memo");
+    output(format!(
+        "gap_memo {} {} {}",
+        gap.coin_id,
+        raw_file.display(),
+        decoded_file.display()
+    ));
+
+    if matches!(decoded.kind, PayloadKind::Json | PayloadKind::Text) {
+        let preview: String = String::from_utf8_lossy(&decoded.data)
+            .chars()
+            .take(200)
+            .collect();
+        info!(%preview, "Memo preview");
     }
 
-    // At this point, we found the parent of the NFT that directly leads back to the metadata (without tracing asserts)
-    input_coins.pop().ok_or(anyhow!("Missing input coin"))
-}
-
-async fn advance_parent(client: &FullnodeClient, coin: &CoinRecord) -> Result<CoinRecord> {
-    client
-        .get_coin_record_by_name(&coin.coin.parent_coin_info)
-        .await?
-        .ok_or(anyhow!("No coin found"))
+    anyhow::Ok(())
 }
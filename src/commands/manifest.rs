@@ -0,0 +1,43 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::manifest::{Manifest, MANIFEST_FILE};
+use std::path::PathBuf;
+use tokio::fs;
+
+#[derive(Args)]
+#[command(
+    about = "Writes the SHA-256 manifest and Merkle root of a recovery, to publish alongside it"
+)]
+pub struct WriteManifest {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The file to write the manifest to [default: manifest.sha256 in the directory]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl WriteManifest {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        let manifest = Manifest::from_dir(dir)?;
+        if manifest.files.is_empty() {
+            anyhow::bail!("No recovered files in {}", dir.display());
+        }
+        let file = self
+            .output
+            .clone()
+            .unwrap_or_else(|| dir.join(MANIFEST_FILE));
+        fs::write(&file, manifest.to_sha256sum()).await?;
+        output(format!(
+            "wrote {} files={} merkle_root={}",
+            file.display(),
+            manifest.files.len(),
+            hex::encode(manifest.merkle_root())
+        ));
+        anyhow::Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::archive::{list_files, IMAGES_DIR};
+use recovery_tools::phash::{HashDistance, ImageHashes};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, info};
+
+#[derive(Args)]
+#[command(about = "Matches reference images to recovered images by perceptual hash")]
+pub struct MatchImages {
+    /// The directory containing the recovered output-images directory [default: the output directory]
+    #[arg(short, long)]
+    recovered: Option<PathBuf>,
+
+    /// The directory of reference images, such as copies downloaded from a marketplace
+    #[arg(long)]
+    reference: PathBuf,
+
+    /// The most bits any of the hashes may differ by for two images to match
+    #[arg(short, long, default_value_t = 10)]
+    threshold: u32,
+
+    /// Also write every reference image's best match to this JSON file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+struct HashedImage {
+    name: String,
+    hashes: ImageHashes,
+}
+
+#[derive(Serialize)]
+struct ImageMatch {
+    reference: String,
+    /// The closest recovered image, if it is within the threshold
+    recovered: Option<String>,
+    /// The closest recovered image, even when it is too far away to match
+    closest: Option<String>,
+    distance: Option<HashDistance>,
+}
+
+impl MatchImages {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let recovered_dir = ctx.dir_or(self.recovered.as_ref());
+        info!(
+            recovered = %recovered_dir.display(),
+            reference = %self.reference.display(),
+            "Hashing images"
+        );
+        let recovered = hash_images(&recovered_dir.join(IMAGES_DIR)).await?;
+        let reference = hash_images(&self.reference).await?;
+        if recovered.is_empty() {
+            anyhow::bail!("No recovered images to match against");
+        }
+
+        let mut matches = vec![];
+        for image in &reference {
+            let closest = recovered
+                .iter()
+                .map(|candidate| (candidate, image.hashes.distance(&candidate.hashes)))
+                .min_by_key(|(_candidate, distance)| distance.total());
+            let matched = closest.filter(|(_candidate, distance)| distance.within(self.threshold));
+
+            match (matched, closest) {
+                (Some((candidate, distance)), _) => output(format!(
+                    "{} -> {} (ahash {}, dhash {}, phash {})",
+                    image.name, candidate.name, distance.ahash, distance.dhash, distance.phash
+                )),
+                (None, Some((candidate, distance))) => output(format!(
+                    "{} -> no match, closest {} (ahash {}, dhash {}, phash {})",
+                    image.name, candidate.name, distance.ahash, distance.dhash, distance.phash
+                )),
+                (None, None) => output(format!("{} -> no match", image.name)),
+            }
+            matches.push(ImageMatch {
+                reference: image.name.clone(),
+                recovered: matched.map(|(candidate, _distance)| candidate.name.clone()),
+                closest: closest.map(|(candidate, _distance)| candidate.name.clone()),
+                distance: closest.map(|(_candidate, distance)| distance),
+            });
+        }
+
+        let matched = matches
+            .iter()
+            .filter(|found| found.recovered.is_some())
+            .count();
+        info!(
+            matched,
+            unmatched = matches.len() - matched,
+            "Matched reference images"
+        );
+
+        if let Some(file) = &self.output {
+            fs::write(file, serde_json::to_vec_pretty(&matches)?).await?;
+            output(format!("wrote {}", file.display()));
+        }
+        anyhow::Ok(())
+    }
+}
+
+/// Hashes every image directly inside `dir`, skipping files that aren't images
+async fn hash_images(dir: &Path) -> Result<Vec<HashedImage>> {
+    let mut images = vec![];
+    for path in list_files(dir)? {
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let data = fs::read(&path).await?;
+        match ImageHashes::of(&data) {
+            Ok(hashes) => images.push(HashedImage { name, hashes }),
+            Err(err) => debug!(file = %name, error = %err, "Skipping file that isn't an image"),
+        }
+    }
+    Ok(images)
+}
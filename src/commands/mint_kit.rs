@@ -0,0 +1,247 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use recovery_tools::archive::{encode_path_segment, load_entries, ArchiveEntry};
+use recovery_tools::{puzzle_hash_from_string, sha256};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{info, warn};
+
+const KIT_IMAGES_DIR: &str = "images";
+const KIT_METADATA_DIR: &str = "metadata";
+const MINT_FILE: &str = "mint.json";
+const BULK_MINT_CSV: &str = "bulk-mint.csv";
+
+#[derive(Args)]
+#[command(about = "Builds a kit to re-host and re-mint a recovered collection")]
+pub struct MintKit {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The directory to write the kit to
+    #[arg(short, long, default_value = "mint-kit")]
+    output: PathBuf,
+
+    /// The URI the kit's images directory will be hosted at, such as ipfs://<cid> or
+    /// https://example.com/images. Can be passed multiple times for mirrors
+    #[arg(long, required = true)]
+    image_base_uri: Vec<String>,
+
+    /// The URI the kit's metadata directory will be hosted at. Can be passed multiple times for
+    /// mirrors
+    #[arg(long, required = true)]
+    metadata_base_uri: Vec<String>,
+
+    /// The URI of the collection's license. Can be passed multiple times for mirrors
+    #[arg(long, requires = "license_file")]
+    license_uri: Vec<String>,
+
+    /// A copy of the license hosted at --license-uri, to record its hash
+    #[arg(long, requires = "license_uri")]
+    license_file: Option<PathBuf>,
+
+    /// The address royalties are paid to
+    #[arg(long)]
+    royalty_address: Option<String>,
+
+    /// The royalty on each trade, in basis points, so 300 is 3%
+    #[arg(long, default_value_t = 0)]
+    royalty_percentage: u16,
+}
+
+/// One NFT to mint, as the wallet's `nft_mint_bulk` request takes it
+#[derive(Serialize)]
+struct MintEntry {
+    uris: Vec<String>,
+    hash: String,
+    meta_uris: Vec<String>,
+    meta_hash: String,
+    license_uris: Vec<String>,
+    license_hash: String,
+    edition_number: u32,
+    edition_total: u32,
+}
+
+#[derive(Serialize)]
+struct Mint {
+    royalty_address: Option<String>,
+    royalty_percentage: u16,
+    metadata_list: Vec<MintEntry>,
+}
+
+impl MintKit {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        info!(
+            dir = %dir.display(),
+            output = %self.output.display(),
+            "Building mint kit"
+        );
+        if let Some(address) = &self.royalty_address {
+            puzzle_hash_from_string(address)?;
+        }
+        if self.royalty_percentage > 10000 {
+            anyhow::bail!("Royalty percentage is in basis points, and can't be over 10000");
+        }
+        let license_hash = match &self.license_file {
+            Some(file) => hex::encode(sha256(&fs::read(file).await?)),
+            None => String::new(),
+        };
+
+        let all_entries = load_entries(dir)?;
+        // Editions that can't be minted still count towards the size of the collection
+        let edition_total = all_entries
+            .iter()
+            .map(|entry| entry.edition)
+            .max()
+            .unwrap_or_default()
+            .max(u32::try_from(all_entries.len())?);
+        let entries: Vec<ArchiveEntry> = all_entries
+            .into_iter()
+            .filter(|entry| {
+                if entry.image.is_none() {
+                    warn!(edition = entry.edition, "Skipping edition with no image");
+                    return false;
+                }
+                if entry.metadata.is_none() {
+                    warn!(edition = entry.edition, "Skipping edition with no metadata");
+                    return false;
+                }
+                true
+            })
+            .collect();
+        if entries.is_empty() {
+            anyhow::bail!(
+                "No editions with both an image and metadata in {}",
+                dir.display()
+            );
+        }
+
+        let images_dir = self.output.join(KIT_IMAGES_DIR);
+        let metadata_dir = self.output.join(KIT_METADATA_DIR);
+        fs::create_dir_all(&images_dir).await?;
+        fs::create_dir_all(&metadata_dir).await?;
+
+        let mut metadata_list = vec![];
+        for entry in &entries {
+            let (uris, hash) =
+                copy_file(entry.image.as_ref(), &images_dir, &self.image_base_uri).await?;
+            let (meta_uris, meta_hash) = copy_file(
+                entry.metadata.as_ref(),
+                &metadata_dir,
+                &self.metadata_base_uri,
+            )
+            .await?;
+            if let Some(path) = &entry.metadata {
+                check_metadata(entry.edition, path).await?;
+            }
+            metadata_list.push(MintEntry {
+                uris,
+                hash,
+                meta_uris,
+                meta_hash,
+                license_uris: self.license_uri.clone(),
+                license_hash: license_hash.clone(),
+                edition_number: entry.edition,
+                edition_total,
+            });
+        }
+
+        self.write_csv(&metadata_list)?;
+        let mint = Mint {
+            royalty_address: self.royalty_address.clone(),
+            royalty_percentage: self.royalty_percentage,
+            metadata_list,
+        };
+        fs::write(
+            self.output.join(MINT_FILE),
+            serde_json::to_vec_pretty(&mint)?,
+        )
+        .await?;
+
+        output(format!(
+            "wrote {} nfts={} edition_total={edition_total}",
+            self.output.display(),
+            mint.metadata_list.len()
+        ));
+        anyhow::Ok(())
+    }
+
+    /// Writes the CSV the bulk mint tools read, with the royalty settings repeated on every row
+    fn write_csv(&self, metadata_list: &[MintEntry]) -> Result<()> {
+        let mut csv = csv::Writer::from_path(self.output.join(BULK_MINT_CSV))?;
+        csv.write_record([
+            "hash",
+            "uris",
+            "meta_hash",
+            "meta_uris",
+            "license_hash",
+            "license_uris",
+            "edition_number",
+            "edition_total",
+            "royalty_address",
+            "royalty_percentage",
+        ])?;
+        let royalty_address = self.royalty_address.clone().unwrap_or_default();
+        let royalty_percentage = self.royalty_percentage.to_string();
+        for entry in metadata_list {
+            csv.write_record([
+                &entry.hash,
+                &entry.uris.join(","),
+                &entry.meta_hash,
+                &entry.meta_uris.join(","),
+                &entry.license_hash,
+                &entry.license_uris.join(","),
+                &entry.edition_number.to_string(),
+                &entry.edition_total.to_string(),
+                &royalty_address,
+                &royalty_percentage,
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+/// Copies a recovered file into the kit, and returns the URIs it will be hosted at under each of
+/// `base_uris`, and its hash
+async fn copy_file(
+    path: Option<&PathBuf>,
+    dir: &Path,
+    base_uris: &[String],
+) -> Result<(Vec<String>, String)> {
+    let Some(path) = path else {
+        return Ok((vec![], String::new()));
+    };
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(anyhow!("File name {} isn't UTF-8", path.display()))?;
+    let data = fs::read(path).await?;
+    fs::write(dir.join(name), &data).await?;
+    let uris = base_uris
+        .iter()
+        .map(|base| {
+            format!(
+                "{}/{}",
+                base.trim_end_matches('/'),
+                encode_path_segment(name)
+            )
+        })
+        .collect();
+    Ok((uris, hex::encode(sha256(&data))))
+}
+
+/// Warns about metadata that a marketplace won't read as CHIP-0007
+async fn check_metadata(edition: u32, path: &Path) -> Result<()> {
+    match serde_json::from_slice::<Value>(&fs::read(path).await?) {
+        Ok(document) if document["format"].as_str() == Some("CHIP-0007") => {}
+        Ok(_) => warn!(edition, "Metadata isn't marked as CHIP-0007"),
+        Err(err) => warn!(edition, error = %err, "Metadata isn't valid JSON"),
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use recovery_tools::archive::IMAGES_DIR;
+use recovery_tools::manifest::Manifest;
+use std::path::PathBuf;
+use tokio::fs;
+
+#[derive(Args)]
+#[command(
+    about = "Writes a proof that one recovered image belongs to the collection's Merkle root"
+)]
+pub struct ProveInclusion {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The name of the image in output-images, such as 12.png
+    image: String,
+
+    /// The file to write the proof to [default: the image's name followed by .proof.json]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl ProveInclusion {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        let manifest = Manifest::from_dir(dir)?;
+        let path = format!("{IMAGES_DIR}/{}", self.image);
+        let proof = manifest.inclusion_proof(&path).ok_or(anyhow!(
+            "No image named {} in {}",
+            self.image,
+            dir.display()
+        ))?;
+
+        let file = self
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.proof.json", self.image)));
+        fs::write(&file, serde_json::to_vec_pretty(&proof)?).await?;
+        output(format!(
+            "wrote {} file={path} index={} leaves={} merkle_root={}",
+            file.display(),
+            proof.index,
+            proof.leaves,
+            proof.root
+        ));
+        anyhow::Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::archive::{image_filename, IMAGES_DIR};
+use recovery_tools::assemble::{ChainRecord, CollectionAssembler};
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+#[derive(Args)]
+#[command(about = "Rebuilds a collection's images from a chain dump, without a node")]
+pub struct Rebuild {
+    /// The JSONL file written by dump-chain
+    #[arg(short, long)]
+    from_dump: PathBuf,
+
+    /// The directory the output-images directory is written to [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The coin ID the dump was started from, which names images without an inscribed filename
+    /// the same way recover-collection does [default: the profile's start_coin, or else the first
+    /// coin in the dump]
+    #[arg(short, long)]
+    coin: Option<String>,
+}
+
+impl Rebuild {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        info!(dump = %self.from_dump.display(), "Rebuilding collection from chain dump");
+        let contents = fs::read_to_string(&self.from_dump).await?;
+        let records = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str::<ChainRecord>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(first) = records.first() else {
+            anyhow::bail!("The chain dump is empty");
+        };
+        let start_coin = ctx
+            .start_coin(self.coin.as_ref())
+            .unwrap_or_else(|_| hex::encode(first.coin_id));
+
+        let outputdir = dir.join(IMAGES_DIR);
+        fs::create_dir_all(&outputdir).await?;
+
+        let mut assembler = CollectionAssembler::new().with_framing(ctx.framing.clone());
+        let mut index = 0;
+        for record in &records {
+            let Some(image) = assembler.push(&record.require_memo(ctx.memo_strategy)?)? else {
+                continue;
+            };
+            index += 1;
+            let final_filename = image_filename(image.filename.as_deref(), || {
+                format!("{index}-{start_coin}.png")
+            });
+            let file = outputdir.join(&final_filename);
+            fs::write(&file, &image.data).await?;
+            debug!(bytes = image.data.len(), "Wrote image");
+            output(format!("wrote {}", file.display()));
+            if assembler.is_finished() {
+                info!(images = index, "Reached end of collection");
+                break;
+            }
+        }
+
+        if !assembler.is_finished() {
+            warn!("The chain dump ends before the end of the collection");
+        }
+        anyhow::Ok(())
+    }
+}
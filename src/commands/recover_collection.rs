@@ -1,95 +1,127 @@
-use crate::chia::client::get_chia_client;
-use crate::chia::image::get_image;
-use crate::chia::memo::parse_memos;
-use anyhow::{anyhow, Result};
-use chia::protocol::Program;
-use chia::traits::Streamable;
+use crate::config::Context;
+use crate::logging::{output, RecoveryProgress};
+use anyhow::Result;
 use clap::Args;
-use dg_xch_clients::api::full_node::FullnodeAPI;
-use dg_xch_core::blockchain::coin::Coin;
-use recovery_tools::{coin_id_from_string, is_collection_end, is_collection_start};
-use std::env;
+use futures::StreamExt;
+use image::ImageFormat;
+use recovery_tools::archive::{image_filename, CONTACT_SHEET, IMAGES_DIR, THUMBNAILS_DIR};
+use recovery_tools::chia::Recoverer;
+use recovery_tools::coin_id_from_string;
+use recovery_tools::thumbnails::{contact_sheet, thumbnail, SheetEntry};
+use std::path::Path;
+use std::pin::pin;
+use std::sync::Arc;
 use tokio::fs;
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
 
 #[derive(Args)]
 #[command(about = "Recover a collection of images")]
 pub struct RecoverCollection {
-    /// The coin ID at the start of the collection
+    /// The coin ID at the start of the collection [default: the profile's start_coin]
     #[arg(short, long)]
-    coin: String,
+    coin: Option<String>,
+
+    /// The number of images in the collection, if known, used to estimate the time remaining
+    #[arg(long)]
+    images: Option<u64>,
+
+    /// Also write a thumbnail of every image, and a contact sheet of all of them
+    #[arg(long)]
+    thumbnails: bool,
+
+    /// The size in pixels of the square each thumbnail is scaled to fit
+    #[arg(long, default_value_t = 128, value_parser = clap::value_parser!(u32).range(16..=1024))]
+    thumbnail_size: u32,
+
+    /// The number of thumbnails in each row of the contact sheet
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+    columns: u32,
 }
 
 impl RecoverCollection {
-    pub async fn execute(&self, port: u16) -> Result<()> {
-        println!("Recovering collection from coin: {}", self.coin);
-        let client = get_chia_client(port);
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let coin = ctx.start_coin(self.coin.as_ref())?;
+        info!(%coin, "Recovering collection");
+        let progress = Arc::new(RecoveryProgress::new(self.images));
+        let recoverer = ctx.recoverer()?.with_observer(progress.clone());
 
-        let cwd = env::current_dir()?;
-        let outputdir = cwd.join("output-images");
+        let dir = &ctx.output_dir;
+        let outputdir = dir.join(IMAGES_DIR);
         fs::create_dir_all(&outputdir).await?;
 
-        let coinid = coin_id_from_string(&self.coin)?;
-        let mut current_coin = client
-            .get_coin_record_by_name(&coinid)
-            .await?
-            .ok_or(anyhow!("No Coin Record found."))?;
-        let mut puzz_solution = client
-            .get_puzzle_and_solution(&coinid, current_coin.spent_block_index)
-            .await?;
+        let filenames = write_collection(&recoverer, &coin, &outputdir).await?;
+        progress.finish();
 
-        let mut found_collection_start = false;
-        let mut current_image_counter = 1;
-
-        while current_coin.spent_block_index > 0 {
-            let solution = puzz_solution.solution.clone();
-            let puzzle = Program::from_bytes(&puzz_solution.puzzle_reveal.to_bytes())?;
-            let solution_program = Program::from_bytes(&solution.to_bytes())?;
-            let memo = parse_memos(&solution_program, &puzzle)?.unwrap();
+        if self.thumbnails {
+            self.write_thumbnails(dir, &filenames).await?;
+        }
 
-            if !found_collection_start && !is_collection_start(&memo) {
-                anyhow::bail!("Not the start of a collection");
-            }
-            found_collection_start = true;
+        anyhow::Ok(())
+    }
 
-            let image_result = get_image(&client, &current_coin, &puzz_solution).await?;
-            let final_filename = image_result
-                .filename
-                .unwrap_or(format!("{}-{}.png", current_image_counter, self.coin));
-            let output_file_name = outputdir.join(&final_filename);
-            let mut file = OpenOptions::new()
-                .write(true) // Open the file for writing
-                .create(true) // Create the file if it does not exist
-                .truncate(true) // Overwrite any existing content
-                .open(&output_file_name)
-                .await?;
-            file.write_all(&image_result.data).await?;
-            println!("Wrote {}", &final_filename);
+    async fn write_thumbnails(&self, dir: &Path, filenames: &[String]) -> Result<()> {
+        let thumbnaildir = dir.join(THUMBNAILS_DIR);
+        fs::create_dir_all(&thumbnaildir).await?;
 
-            if is_collection_end(&image_result.last_memo) {
-                println!("Reached end of collection!");
-                return Ok(());
+        let mut entries = vec![];
+        for (index, filename) in filenames.iter().enumerate() {
+            let data = fs::read(dir.join(IMAGES_DIR).join(filename)).await?;
+            let caption = format!("#{} {filename}", index + 1);
+            match thumbnail(&data, self.thumbnail_size) {
+                Ok(image) => {
+                    image.save_with_format(thumbnaildir.join(filename), ImageFormat::Png)?;
+                    entries.push(SheetEntry {
+                        caption,
+                        thumbnail: Some(image),
+                    });
+                }
+                Err(err) => {
+                    warn!(file = %filename, error = %err, "Unable to decode image");
+                    entries.push(SheetEntry {
+                        caption,
+                        thumbnail: None,
+                    });
+                }
             }
-            let child_coin = Coin {
-                parent_coin_info: image_result.last_coin.coin.coin_id(),
-                puzzle_hash: image_result.last_coin.coin.puzzle_hash,
-                amount: image_result.last_coin.coin.amount,
-            };
-            current_coin = client
-                .get_coin_record_by_name(&child_coin.name())
-                .await?
-                .unwrap();
-            if current_coin.spent_block_index == 0 {
-                println!("No more data available on chain, but did not reach end of collection!");
-                return Ok(());
-            }
-            puzz_solution = client
-                .get_puzzle_and_solution(&child_coin.name(), current_coin.spent_block_index)
-                .await?;
-            current_image_counter += 1;
         }
 
+        let sheet = contact_sheet(&entries, self.thumbnail_size, self.columns);
+        let file = dir.join(CONTACT_SHEET);
+        sheet.save_with_format(&file, ImageFormat::Png)?;
+        let broken = entries
+            .iter()
+            .filter(|entry| entry.thumbnail.is_none())
+            .count();
+        output(format!(
+            "wrote {} images={} broken={broken}",
+            file.display(),
+            entries.len()
+        ));
         anyhow::Ok(())
     }
 }
+
+/// Recovers every image in the collection that starts at `start_coin`, writing each one to `outputdir`.
+/// Returns the names of the files written, in collection order
+pub async fn write_collection(
+    recoverer: &Recoverer,
+    start_coin: &str,
+    outputdir: &Path,
+) -> Result<Vec<String>> {
+    let coin_id = coin_id_from_string(start_coin)?;
+    let mut images = pin!(recoverer.recover_collection(&coin_id));
+    let mut filenames = vec![];
+    while let Some(image) = images.next().await {
+        let image = image?;
+        let final_filename = image_filename(image.filename.as_deref(), || {
+            format!("{}-{start_coin}.png", image.index)
+        });
+        let file = outputdir.join(&final_filename);
+        fs::write(&file, &image.data).await?;
+        debug!(bytes = image.data.len(), "Wrote image");
+        output(format!("wrote {}", file.display()));
+        filenames.push(final_filename);
+    }
+
+    anyhow::Ok(filenames)
+}
@@ -1,13 +1,12 @@
-use crate::chia::client::get_chia_client;
-use crate::chia::image::get_image;
-use anyhow::{anyhow, Result};
+use crate::config::Context;
+use crate::logging::{output, RecoveryProgress};
+use anyhow::Result;
 use clap::Args;
-use dg_xch_clients::api::full_node::FullnodeAPI;
+use recovery_tools::archive::{image_filename, IMAGES_DIR};
 use recovery_tools::coin_id_from_string;
-use std::env;
+use std::sync::Arc;
 use tokio::fs;
-use tokio::fs::OpenOptions;
-use tokio::io::AsyncWriteExt;
+use tracing::{debug, info};
 
 #[derive(Args)]
 #[command(about = "Recover a single image")]
@@ -18,35 +17,24 @@ pub struct RecoverImage {
 }
 
 impl RecoverImage {
-    pub async fn execute(&self, port: u16) -> Result<()> {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
         let Self { coin } = self;
-        println!("Recovering image from coin: {coin}");
-        let client = get_chia_client(port);
+        info!(%coin, "Recovering image");
+        let progress = Arc::new(RecoveryProgress::new(Some(1)));
+        let recoverer = ctx.recoverer()?.with_observer(progress.clone());
 
-        let coinid = coin_id_from_string(coin)?;
-        let current_coin = client
-            .get_coin_record_by_name(&coinid)
-            .await?
-            .ok_or(anyhow!("No Coin Record found."))?;
-        let puzz_solution = client
-            .get_puzzle_and_solution(&coinid, current_coin.spent_block_index)
-            .await?;
-
-        let cwd = env::current_dir()?;
-        let outputdir = cwd.join("output-images");
+        let dir = &ctx.output_dir;
+        let outputdir = dir.join(IMAGES_DIR);
         fs::create_dir_all(&outputdir).await?;
 
-        let image_result = get_image(&client, &current_coin, &puzz_solution).await?;
-        let final_filename = image_result.filename.unwrap_or(format!("{coin}.png"));
-        let output_file_name = outputdir.join(&final_filename);
-        let mut file = OpenOptions::new()
-            .write(true) // Open the file for writing
-            .create(true) // Create the file if it does not exist
-            .truncate(true) // Overwrite any existing content
-            .open(&output_file_name)
-            .await?;
-        file.write_all(&image_result.data).await?;
-        println!("Wrote {}", &final_filename);
+        let image = recoverer.recover_image(&coin_id_from_string(coin)?).await?;
+        progress.finish();
+
+        let final_filename = image_filename(image.filename.as_deref(), || format!("{coin}.png"));
+        let file = outputdir.join(&final_filename);
+        fs::write(&file, &image.data).await?;
+        debug!(bytes = image.data.len(), "Wrote image");
+        output(format!("wrote {}", file.display()));
 
         anyhow::Ok(())
     }
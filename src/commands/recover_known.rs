@@ -0,0 +1,122 @@
+use crate::commands::recover_collection::write_collection;
+use crate::commands::recover_metadata::write_metadata;
+use crate::config::Context;
+use crate::logging::{output, RecoveryProgress};
+use anyhow::{anyhow, Result};
+use clap::Args;
+use recovery_tools::archive::{IMAGES_DIR, METADATA_DIR};
+use recovery_tools::chia::Recoverer;
+use recovery_tools::known::find_known;
+use recovery_tools::manifest::{Manifest, Mismatch, MANIFEST_FILE};
+use std::sync::Arc;
+use tokio::fs;
+use tracing::{info, warn};
+
+#[derive(Args)]
+#[command(about = "Recovers a known collection by name, and checks it against the registry")]
+pub struct RecoverKnown {
+    /// The name of the collection, as shown by `known list`
+    name: String,
+}
+
+impl RecoverKnown {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let known = find_known(&self.name).ok_or(anyhow!(
+            "No known collection named {}, see `known list`",
+            self.name
+        ))?;
+        info!(
+            name = known.name,
+            title = known.title,
+            "Recovering known collection"
+        );
+        let progress = Arc::new(RecoveryProgress::new(known.images));
+        let recoverer = ctx.cached(
+            Recoverer::new(ctx.client()?, known.memo_strategy)
+                .with_framing(known.framing.clone())
+                .with_observer(progress.clone()),
+        );
+
+        let dir = &ctx.output_dir;
+        let outputdir = dir.join(IMAGES_DIR);
+        fs::create_dir_all(&outputdir).await?;
+        let filenames = write_collection(&recoverer, known.start_coin, &outputdir).await?;
+        progress.finish();
+        let metadata = match known.metadata_coin {
+            Some(metadata_coin) => {
+                Some(write_metadata(&recoverer, metadata_coin, &dir.join(METADATA_DIR)).await?)
+            }
+            None => None,
+        };
+
+        let manifest = Manifest::from_dir(dir)?;
+        let file = dir.join(MANIFEST_FILE);
+        fs::write(&file, manifest.to_sha256sum()).await?;
+        output(format!(
+            "wrote {} files={} merkle_root={}",
+            file.display(),
+            manifest.files.len(),
+            hex::encode(manifest.merkle_root())
+        ));
+
+        let mut problems = 0;
+        // Every image has its own metadata, so the two counts check each other even when the
+        // registry has neither
+        if let Some(metadata) = metadata {
+            if metadata == filenames.len() {
+                info!(images = metadata, "Every image has metadata");
+            } else {
+                warn!(
+                    images = filenames.len(),
+                    metadata, "The number of images doesn't match the number of metadata files"
+                );
+                problems += 1;
+            }
+        }
+        if known.images.is_none() || known.manifest.is_none() {
+            info!(
+                images = filenames.len(),
+                manifest = %dir.join(MANIFEST_FILE).display(),
+                "The registry doesn't have this collection's image count or hashes yet, once this \
+                 recovery has been checked against an independent copy they can be added to it"
+            );
+        }
+        if let Some(expected) = known.images {
+            if filenames.len() as u64 == expected {
+                info!(images = expected, "Image count matches the registry");
+            } else {
+                warn!(
+                    images = filenames.len(),
+                    expected, "Image count doesn't match the registry"
+                );
+                problems += 1;
+            }
+        }
+        match known.expected_manifest()? {
+            Some(expected) => {
+                let mismatches = manifest.check(&expected);
+                for mismatch in &mismatches {
+                    match mismatch {
+                        Mismatch::Missing(path) => warn!(file = %path, "Missing file"),
+                        Mismatch::Different(path) => {
+                            warn!(file = %path, "File doesn't match the registry");
+                        }
+                        Mismatch::Extra(path) => {
+                            warn!(file = %path, "File isn't in the registry");
+                        }
+                    }
+                }
+                if mismatches.is_empty() {
+                    info!(files = expected.files.len(), "Every file matches the registry");
+                }
+                problems += mismatches.len();
+            }
+            None => warn!("The registry has no expected hashes for this collection, so the files weren't checked"),
+        }
+
+        if problems > 0 {
+            anyhow::bail!("The recovery doesn't match the registry, {problems} problems found");
+        }
+        anyhow::Ok(())
+    }
+}
@@ -1,80 +1,69 @@
-use crate::chia::client::get_chia_client;
-use crate::chia::memo::parse_memos;
-use anyhow::anyhow;
-use base64::{engine::general_purpose, Engine};
-use chia::protocol::Program;
-use chia::traits::Streamable;
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
 use clap::Args;
-use dg_xch_clients::api::full_node::FullnodeAPI;
-use recovery_tools::{
-    coin_id_from_string, decompress_gzip_to_bytes, filter_meta_end, filter_meta_start, is_meta,
-};
-use std::env;
+use recovery_tools::archive::METADATA_DIR;
+use recovery_tools::chia::Recoverer;
+use recovery_tools::coin_id_from_string;
+use std::path::Path;
 use tokio::fs;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
 
 #[derive(Args)]
 #[command(about = "Recover metadata for the collection")]
 pub struct RecoverMetadata {
-    /// The coin ID that contains the metadata
+    /// The coin ID that contains the metadata [default: the profile's metadata_coin]
     #[arg(short, long)]
-    coin: String,
+    coin: Option<String>,
 }
 
 impl RecoverMetadata {
-    pub async fn execute(&self, port: u16) -> anyhow::Result<()> {
-        println!(
-            "Recovering metadata for collection from coin: {}",
-            self.coin
-        );
-        let client = get_chia_client(port);
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let coin = ctx.metadata_coin(self.coin.as_ref())?;
+        info!(%coin, "Recovering metadata for collection");
+        let recoverer = ctx.recoverer()?;
+        write_metadata(&recoverer, &coin, &ctx.output_dir.join(METADATA_DIR)).await?;
 
-        let coinid = coin_id_from_string(&self.coin)?;
-        let current_coin = client
-            .get_coin_record_by_name(&coinid)
-            .await?
-            .ok_or(anyhow!("No Coin Record found."))?;
-        let puzz_solution = client
-            .get_puzzle_and_solution(&coinid, current_coin.spent_block_index)
-            .await?;
-
-        let solution = puzz_solution.solution.clone();
-        let puzzle = Program::from_bytes(&puzz_solution.puzzle_reveal.to_bytes())?;
-        let solution_program = Program::from_bytes(&solution.to_bytes())?;
-        let mut memo = parse_memos(&solution_program, &puzzle)?.unwrap();
-
-        if !is_meta(&memo) {
-            anyhow::bail!("Not a metadata coin")
-        }
-
-        let cwd = env::current_dir()?;
-        let outputdir = cwd.join("output-metadata");
-        fs::create_dir_all(&outputdir).await?;
-
-        // Remove start and end meta markers
-        memo = filter_meta_start(&memo);
-        memo = filter_meta_end(&memo);
-
-        // Decompress
-        let decompressed_data = decompress_gzip_to_bytes(&memo)?;
-
-        // Parse the JSON data as an array of RawMessage (serde_json::Value)
-        let all_meta: Vec<String> = serde_json::from_slice(&decompressed_data)?;
+        anyhow::Ok(())
+    }
+}
 
-        // Iterate over each item in the array and write to a separate JSON file
-        for (index, item) in all_meta.iter().enumerate() {
-            let output_file = outputdir.join(format!("metadata_{:03}.json", index + 1));
-            let mut file = File::create(output_file).await?;
+/// Recovers the metadata that starts at `coin`, writing each NFT's document to `outputdir`.
+/// Returns the number of files written
+pub async fn write_metadata(recoverer: &Recoverer, coin: &str, outputdir: &Path) -> Result<usize> {
+    let all_meta = recoverer
+        .recover_metadata(&coin_id_from_string(coin)?)
+        .await?;
+    write_metadata_documents(&all_meta, outputdir).await
+}
 
-            // Check if the item is a string
-            //if let Some(base64_string) = item.as_str() {
-            let decoded_bytes = general_purpose::STANDARD.decode(item)?;
+/// Writes each NFT's metadata document to `outputdir`, skipping entries that couldn't be decoded.
+/// Returns the number of files written
+pub async fn write_metadata_documents(
+    all_meta: &[Result<Vec<u8>>],
+    outputdir: &Path,
+) -> Result<usize> {
+    fs::create_dir_all(outputdir).await?;
 
-            // Write the decoded bytes to a file
-            file.write_all(&decoded_bytes).await?;
+    // Write each NFT's metadata to a separate JSON file
+    let mut written = 0;
+    for (index, item) in all_meta.iter().enumerate() {
+        let entry = index + 1;
+        match item {
+            Ok(document) => {
+                let output_file = outputdir.join(format!("metadata_{entry:03}.json"));
+                fs::write(&output_file, document).await?;
+                output(format!("wrote {}", output_file.display()));
+                written += 1;
+            }
+            Err(err) => warn!(entry, error = %err, "Skipping metadata entry"),
         }
-
-        anyhow::Ok(())
     }
+    info!(
+        files = written,
+        failed = all_meta.len() - written,
+        "Wrote metadata"
+    );
+
+    anyhow::Ok(written)
 }
@@ -0,0 +1,252 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use futures::StreamExt;
+use recovery_tools::assemble::CollectionAssembler;
+use recovery_tools::chia::{ChainLink, Recoverer};
+use recovery_tools::coin_id_from_string;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::pin::pin;
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+#[derive(Args)]
+#[command(
+    about = "Reports how a collection is stored on chain, image by image",
+    long_about = "Reports how a collection is stored on chain, image by image.\n\n\
+        Fees aren't reported. A fee is paid by a whole spend bundle, and a block doesn't record \
+        which of its spends were bundled together, so the share of a fee paid for a collection's \
+        spends can't be worked out from the chain. Each spend's released mojos are reported \
+        instead: what it didn't pass on to the coins it created. They're the fee only when no \
+        other coin in its bundle paid or took mojos."
+)]
+pub struct Report {
+    /// The coin ID at the start of the collection [default: the profile's start_coin]
+    #[arg(short, long)]
+    coin: Option<String>,
+
+    /// Also write the full report, including every spend, to this JSON file
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct SpendReport {
+    coin_id: Bytes32,
+    height: u32,
+    /// Bytes of image data carried by the spend
+    data_bytes: usize,
+    /// Estimated CLVM cost, without the block generator's share
+    estimated_cost: u64,
+    /// Mojos not passed on to the coins the spend created, which may be paid by or to other coins
+    /// in the same spend bundle, so this isn't necessarily the fee
+    released_mojos: u64,
+    /// Size of the serialized puzzle reveal and solution
+    spend_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct ImageReport {
+    index: usize,
+    start_coin: Bytes32,
+    filename: Option<String>,
+    stats: Stats,
+    spends: Vec<SpendReport>,
+}
+
+#[derive(Serialize)]
+struct CollectionReport {
+    start_coin: Bytes32,
+    complete: bool,
+    stats: Stats,
+    images: Vec<ImageReport>,
+}
+
+#[derive(Default, Serialize)]
+struct Stats {
+    spends: usize,
+    data_bytes: usize,
+    bytes_per_spend: f64,
+    first_height: u32,
+    last_height: u32,
+    first_timestamp: Option<u64>,
+    last_timestamp: Option<u64>,
+    estimated_cost: u64,
+    released_mojos: u64,
+    /// Total size of the puzzle reveals and solutions, which is what the chain stores
+    footprint_bytes: usize,
+}
+
+impl Stats {
+    fn add(&mut self, spend: &SpendReport) {
+        if self.spends == 0 {
+            self.first_height = spend.height;
+        }
+        self.spends += 1;
+        self.data_bytes += spend.data_bytes;
+        self.bytes_per_spend = self.data_bytes as f64 / self.spends as f64;
+        self.last_height = spend.height;
+        self.estimated_cost += spend.estimated_cost;
+        self.released_mojos += spend.released_mojos;
+        self.footprint_bytes += spend.spend_bytes;
+    }
+}
+
+impl Report {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let coin = ctx.start_coin(self.coin.as_ref())?;
+        info!(%coin, "Reporting on collection");
+        let recoverer = ctx.recoverer()?;
+        let start_coin = coin_id_from_string(&coin)?;
+        let mut timestamps = Timestamps::new(&recoverer);
+
+        let mut assembler = CollectionAssembler::new().with_framing(ctx.framing.clone());
+        let mut images = vec![];
+        let mut spends = vec![];
+        let mut image_start = start_coin;
+        let mut links = pin!(recoverer.walk_chain(&start_coin));
+        while let Some(link) = links.next().await {
+            let link = link?;
+            let memo = link.require_memo()?;
+            if assembler.at_image_start() {
+                image_start = link.coin.coin.name();
+            }
+            let spend = spend_report(&link, memo.len());
+            debug!(
+                coin = %spend.coin_id,
+                height = spend.height,
+                data_bytes = spend.data_bytes,
+                estimated_cost = spend.estimated_cost,
+                released_mojos = spend.released_mojos,
+                spend_bytes = spend.spend_bytes,
+                "Spend"
+            );
+            spends.push(spend);
+
+            if let Some(assembled) = assembler.push(memo)? {
+                let mut stats = Stats::default();
+                spends.iter().for_each(|spend| stats.add(spend));
+                timestamps.fill(&mut stats).await?;
+                let image = ImageReport {
+                    index: images.len() + 1,
+                    start_coin: image_start,
+                    filename: assembled.filename,
+                    stats,
+                    spends: std::mem::take(&mut spends),
+                };
+                print_stats("image", Some(&image), &image.stats);
+                images.push(image);
+                if assembler.is_finished() {
+                    break;
+                }
+            }
+        }
+
+        let complete = assembler.is_finished();
+        if !complete {
+            warn!("No more data available on chain, but did not reach end of collection");
+        }
+        let mut stats = Stats::default();
+        images
+            .iter()
+            .flat_map(|image| &image.spends)
+            .chain(&spends)
+            .for_each(|spend| stats.add(spend));
+        timestamps.fill(&mut stats).await?;
+        print_stats("collection", None, &stats);
+
+        if let Some(file) = &self.output {
+            let report = CollectionReport {
+                start_coin,
+                complete,
+                stats,
+                images,
+            };
+            fs::write(file, serde_json::to_vec_pretty(&report)?).await?;
+            output(format!("wrote {}", file.display()));
+        }
+        anyhow::Ok(())
+    }
+}
+
+fn spend_report(link: &ChainLink, data_bytes: usize) -> SpendReport {
+    SpendReport {
+        coin_id: link.coin.coin.name(),
+        height: link.coin.spent_block_index,
+        data_bytes,
+        estimated_cost: link.estimated_cost,
+        released_mojos: link.released,
+        spend_bytes: link.spend_bytes,
+    }
+}
+
+/// Prints one line of statistics, for an image or the whole collection
+fn print_stats(label: &str, image: Option<&ImageReport>, stats: &Stats) {
+    let mut line = String::from(label);
+    if let Some(image) = image {
+        line.push_str(&format!(" {} start_coin={}", image.index, image.start_coin));
+        if let Some(filename) = &image.filename {
+            line.push_str(&format!(" filename={filename:?}"));
+        }
+    }
+    let timestamp = |timestamp: Option<u64>| timestamp.map_or("-".to_string(), |t| t.to_string());
+    line.push_str(&format!(
+        " spends={} data_bytes={} bytes_per_spend={:.1} first_height={} last_height={} \
+         first_timestamp={} last_timestamp={} estimated_cost={} released_mojos={} \
+         footprint_bytes={}",
+        stats.spends,
+        stats.data_bytes,
+        stats.bytes_per_spend,
+        stats.first_height,
+        stats.last_height,
+        timestamp(stats.first_timestamp),
+        timestamp(stats.last_timestamp),
+        stats.estimated_cost,
+        stats.released_mojos,
+        stats.footprint_bytes,
+    ));
+    output(line);
+}
+
+/// Looks up the timestamps of the blocks spends were made in, once per height
+struct Timestamps<'a> {
+    recoverer: &'a Recoverer,
+    cache: HashMap<u32, Option<u64>>,
+}
+
+impl<'a> Timestamps<'a> {
+    fn new(recoverer: &'a Recoverer) -> Self {
+        Self {
+            recoverer,
+            cache: HashMap::new(),
+        }
+    }
+
+    async fn fill(&mut self, stats: &mut Stats) -> Result<()> {
+        if stats.spends == 0 {
+            return Ok(());
+        }
+        stats.first_timestamp = self.at(stats.first_height).await?;
+        stats.last_timestamp = self.at(stats.last_height).await?;
+        Ok(())
+    }
+
+    async fn at(&mut self, height: u32) -> Result<Option<u64>> {
+        if let Some(timestamp) = self.cache.get(&height) {
+            return Ok(*timestamp);
+        }
+        let block = self
+            .recoverer
+            .client()
+            .get_block_record_by_height(height)
+            .await
+            .map_err(|err| anyhow!("Unable to get block {height}: {err}"))?;
+        self.cache.insert(height, block.timestamp);
+        Ok(block.timestamp)
+    }
+}
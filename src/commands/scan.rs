@@ -0,0 +1,244 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use recovery_tools::chia::coins::conditions_for_coin;
+use recovery_tools::chia::memo::parse_memos_from_conditions;
+use recovery_tools::{find_markers, Marker, MarkerMatch};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, info, warn};
+
+#[derive(Args)]
+#[command(about = "Scans a range of blocks for inscriptions framed by known markers")]
+pub struct Scan {
+    /// The first block height to scan
+    #[arg(long)]
+    from_height: u32,
+
+    /// The last block height to scan (inclusive)
+    #[arg(long)]
+    to_height: u32,
+
+    /// JSONL file results are appended to. Progress is saved alongside it so scans can resume
+    #[arg(short, long, default_value = "scan-results.jsonl")]
+    output: PathBuf,
+}
+
+#[derive(Serialize)]
+struct ScanResult {
+    height: u32,
+    coin_id: Bytes32,
+    parent_coin_id: Bytes32,
+    puzzle_hash: Bytes32,
+    amount: u64,
+    memo_length: usize,
+    markers: Vec<MarkerMatch>,
+}
+
+impl Scan {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        if self.from_height > self.to_height {
+            anyhow::bail!("--from-height must not be greater than --to-height");
+        }
+        let client = ctx.client()?;
+
+        let progress_file = PathBuf::from(format!("{}.progress", self.output.display()));
+        let mut start_height = self.from_height;
+        if let Ok(contents) = fs::read_to_string(&progress_file).await {
+            if let Ok(last_height) = contents.trim().parse::<u32>() {
+                if (self.from_height..=self.to_height).contains(&last_height) {
+                    start_height = last_height.saturating_add(1);
+                    info!(last_height, "Resuming scan");
+                }
+            }
+        }
+        info!(
+            from_height = start_height,
+            to_height = self.to_height,
+            output = %self.output.display(),
+            "Scanning blocks"
+        );
+        // Rows for blocks past the saved progress were written by a run that stopped before it
+        // could record the block as done, and the block is about to be scanned again
+        drop_rows(&self.output, start_height..=self.to_height).await?;
+
+        let mut results = OpenOptions::new()
+            .create(true) // Create the file if it does not exist
+            .append(true) // Keep results from earlier runs of the same scan
+            .open(&self.output)
+            .await?;
+
+        let mut found = 0;
+        for height in start_height..=self.to_height {
+            let block = client.get_block_record_by_height(height).await?;
+            // Only transaction blocks have spends
+            debug!(height, "Scanning block");
+            if block.timestamp.is_some() {
+                let (_additions, removals) = client
+                    .get_additions_and_removals(&block.header_hash)
+                    .await?;
+                for removal in removals {
+                    let memo =
+                        conditions_for_coin(&client, &removal)
+                            .await
+                            .and_then(|conditions| {
+                                parse_memos_from_conditions(conditions, ctx.memo_strategy)
+                            });
+                    let memo = match memo {
+                        Ok(Some(memo)) => memo,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            warn!(
+                                coin = %removal.coin.name(),
+                                height,
+                                error = %err,
+                                "Skipping coin"
+                            );
+                            continue;
+                        }
+                    };
+
+                    let markers = find_markers(&memo);
+                    if !markers.iter().any(|found| {
+                        matches!(
+                            found.marker,
+                            Marker::CollectionStart | Marker::MetaStart | Marker::PngStart
+                        )
+                    }) {
+                        continue;
+                    }
+
+                    let names: Vec<&str> =
+                        markers.iter().map(|found| found.marker.name()).collect();
+                    output(format!(
+                        "{height} {} {}",
+                        removal.coin.name(),
+                        names.join(",")
+                    ));
+                    let result = ScanResult {
+                        height,
+                        coin_id: removal.coin.name(),
+                        parent_coin_id: removal.coin.parent_coin_info,
+                        puzzle_hash: removal.coin.puzzle_hash,
+                        amount: removal.coin.amount,
+                        memo_length: memo.len(),
+                        markers,
+                    };
+                    let mut line = serde_json::to_string(&result)?;
+                    line.push('\n');
+                    results.write_all(line.as_bytes()).await?;
+                    found += 1;
+                }
+            }
+
+            // The block's rows must be on disk before it is recorded as done
+            results.flush().await?;
+            results.sync_data().await?;
+            write_atomically(&progress_file, height.to_string().as_bytes()).await?;
+        }
+
+        info!(found, "Scan complete");
+        anyhow::Ok(())
+    }
+}
+
+/// Removes the rows for blocks in `heights` from the results file, if it exists
+async fn drop_rows(output: &Path, heights: std::ops::RangeInclusive<u32>) -> Result<()> {
+    let Ok(contents) = fs::read_to_string(output).await else {
+        return Ok(());
+    };
+    let (kept, dropped) = kept_rows(&contents, &heights);
+    if dropped > 0 {
+        warn!(rows = dropped, "Dropping rows from an interrupted scan");
+        write_atomically(output, kept.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// The lines of `contents` that aren't rows for blocks in `heights`, and how many were dropped.
+/// A last line that can't be parsed was cut short by a crash while its block was being written,
+/// so it's dropped and scanned again with its block. Any other line that can't be parsed isn't
+/// ours to remove, and is kept
+fn kept_rows(contents: &str, heights: &std::ops::RangeInclusive<u32>) -> (String, usize) {
+    #[derive(Deserialize)]
+    struct Row {
+        height: u32,
+    }
+
+    let mut kept = String::with_capacity(contents.len());
+    let mut dropped = 0;
+    let mut lines = contents.lines().enumerate().peekable();
+    while let Some((number, line)) = lines.next() {
+        match serde_json::from_str::<Row>(line) {
+            Ok(row) if heights.contains(&row.height) => dropped += 1,
+            Err(_) if lines.peek().is_none() => {
+                warn!(line = number + 1, "Dropping a truncated row");
+                dropped += 1;
+            }
+            Err(err) => {
+                warn!(line = number + 1, error = %err, "Keeping a row that can't be read");
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            Ok(_) => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+        }
+    }
+    (kept, dropped)
+}
+
+/// Writes `path` through a temporary file, so a crash leaves either the old or the new contents
+pub async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let temp = PathBuf::from(format!("{}.tmp", path.display()));
+    let mut file = fs::File::create(&temp).await?;
+    file.write_all(contents).await?;
+    file.sync_data().await?;
+    fs::rename(&temp, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_rows_before_the_range() {
+        let contents = "{\"height\":1,\"coin\":\"a\"}\n{\"height\":5}\n{\"height\":6}\n";
+        let (kept, dropped) = kept_rows(contents, &(5..=10));
+        assert_eq!(kept, "{\"height\":1,\"coin\":\"a\"}\n");
+        assert_eq!(dropped, 2);
+
+        let (kept, dropped) = kept_rows(contents, &(7..=10));
+        assert_eq!(kept, contents);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn drops_a_truncated_last_row() {
+        let contents = "{\"height\":1}\n{\"height\":2}\n{\"hei";
+        let (kept, dropped) = kept_rows(contents, &(3..=10));
+        assert_eq!(kept, "{\"height\":1}\n{\"height\":2}\n");
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn keeps_unreadable_rows_before_the_last() {
+        let contents = "{\"height\":1}\nnot a row\n{\"height\":2}\n";
+        let (kept, dropped) = kept_rows(contents, &(3..=10));
+        assert_eq!(kept, contents);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn empty_file() {
+        assert_eq!(kept_rows("", &(0..=10)), (String::new(), 0));
+    }
+}
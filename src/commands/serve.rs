@@ -0,0 +1,348 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use axum::extract::{Path as UrlPath, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Json, Response};
+use axum::routing::get;
+use axum::Router;
+use clap::Args;
+use recovery_tools::archive::{
+    encode_path_segment, load_entries, ArchiveEntry, IMAGES_DIR, METADATA_DIR,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::fs;
+use tokio::net::TcpListener;
+use tracing::info;
+
+#[derive(Args)]
+#[command(about = "Serves a recovered collection as a local web gallery")]
+pub struct Serve {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The address the gallery listens on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+}
+
+impl Serve {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        let root = Arc::new(Gallery {
+            dir: dir.to_path_buf(),
+            metadata: Mutex::default(),
+        });
+        let app = Router::new()
+            .route("/", get(gallery))
+            .route("/nft/:edition", get(detail))
+            .route("/api/nfts", get(api_nfts))
+            .route("/api/nfts/:edition", get(api_nft))
+            .route("/images/:file", get(image_file))
+            .route("/metadata/:file", get(metadata_file))
+            .route("/files/:file", get(root_file))
+            .with_state(root);
+
+        let listener = TcpListener::bind(&self.listen).await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        info!(dir = %dir.display(), "Serving gallery");
+        output(url);
+        axum::serve(listener, app).await?;
+
+        anyhow::Ok(())
+    }
+}
+
+/// Metadata that was read, or the reason it couldn't be, and when its file was modified
+type CachedMetadata = (SystemTime, Result<Value, String>);
+
+/// The directory being served, and the metadata read from it so far
+struct Gallery {
+    dir: PathBuf,
+    /// Metadata by file, so each file is only read again when it changes
+    metadata: Mutex<HashMap<PathBuf, CachedMetadata>>,
+}
+
+type Root = Arc<Gallery>;
+
+struct ServeError(anyhow::Error);
+
+impl IntoResponse for ServeError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for ServeError {
+    fn from(err: E) -> Self {
+        Self(err.into())
+    }
+}
+
+#[derive(Serialize)]
+struct NftSummary {
+    edition: u32,
+    name: Option<String>,
+    image_url: Option<String>,
+    metadata_url: Option<String>,
+    /// Why the metadata couldn't be read, if it couldn't
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NftDetail {
+    #[serde(flatten)]
+    summary: NftSummary,
+    metadata: Option<Value>,
+}
+
+async fn read_metadata(gallery: &Gallery, path: &Path) -> Result<Value, String> {
+    let modified = fs::metadata(path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .map_err(|err| err.to_string())?;
+    if let Some((cached_modified, metadata)) = gallery.metadata.lock().unwrap().get(path) {
+        if *cached_modified == modified {
+            return metadata.clone();
+        }
+    }
+
+    let metadata = match fs::read(path).await {
+        Ok(data) => serde_json::from_slice(&data).map_err(|err| err.to_string()),
+        Err(err) => Err(err.to_string()),
+    };
+    gallery
+        .metadata
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (modified, metadata.clone()));
+    metadata
+}
+
+fn file_url(prefix: &str, path: Option<&PathBuf>) -> Option<String> {
+    path.and_then(|path| path.file_name())
+        .and_then(|name| name.to_str())
+        .map(|name| format!("/{prefix}/{}", encode_path_segment(name)))
+}
+
+/// Loads an NFT for display. Metadata that can't be read is reported on the NFT rather than
+/// failing the page it is shown on
+async fn load_nft(gallery: &Gallery, entry: &ArchiveEntry) -> NftDetail {
+    let (metadata, error) = match &entry.metadata {
+        Some(path) => match read_metadata(gallery, path).await {
+            Ok(metadata) => (Some(metadata), None),
+            Err(err) => (None, Some(err)),
+        },
+        None => (None, None),
+    };
+    let name = metadata
+        .as_ref()
+        .and_then(|metadata| metadata["name"].as_str())
+        .map(String::from);
+    NftDetail {
+        summary: NftSummary {
+            edition: entry.edition,
+            name,
+            image_url: file_url("images", entry.image.as_ref()),
+            metadata_url: file_url("metadata", entry.metadata.as_ref()),
+            error,
+        },
+        metadata,
+    }
+}
+
+async fn find_nft(gallery: &Gallery, edition: u32) -> Result<Option<NftDetail>> {
+    let entries = load_entries(&gallery.dir)?;
+    match entries.iter().find(|entry| entry.edition == edition) {
+        Some(entry) => Ok(Some(load_nft(gallery, entry).await)),
+        None => Ok(None),
+    }
+}
+
+async fn api_nfts(State(root): State<Root>) -> Result<Json<Vec<NftSummary>>, ServeError> {
+    let mut nfts = vec![];
+    for entry in load_entries(&root.dir)? {
+        nfts.push(load_nft(&root, &entry).await.summary);
+    }
+    Ok(Json(nfts))
+}
+
+async fn api_nft(
+    State(root): State<Root>,
+    UrlPath(edition): UrlPath<u32>,
+) -> Result<Response, ServeError> {
+    Ok(match find_nft(&root, edition).await? {
+        Some(nft) => Json(nft).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
+}
+
+async fn gallery(State(root): State<Root>) -> Result<Html<String>, ServeError> {
+    let mut cards = String::new();
+    for entry in load_entries(&root.dir)? {
+        let nft = load_nft(&root, &entry).await.summary;
+        let title = nft.name.unwrap_or_else(|| format!("#{}", nft.edition));
+        let image = nft.image_url.map_or_else(
+            || String::from("<div class=\"missing\">No image</div>"),
+            |url| format!("<img src=\"{url}\" loading=\"lazy\">"),
+        );
+        let error = nft.error.map_or_else(String::new, |err| {
+            format!("<span class=\"error\">{}</span>", escape_html(&err))
+        });
+        cards.push_str(&format!(
+            "<a class=\"card\" href=\"/nft/{}\">{image}<span>{}</span>{error}</a>\n",
+            nft.edition,
+            escape_html(&title)
+        ));
+    }
+
+    Ok(Html(page(
+        "Recovered Collection",
+        &format!("<h1>Recovered Collection</h1>\n<div class=\"grid\">\n{cards}</div>"),
+    )))
+}
+
+async fn detail(
+    State(root): State<Root>,
+    UrlPath(edition): UrlPath<u32>,
+) -> Result<Response, ServeError> {
+    let Some(nft) = find_nft(&root, edition).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let title = nft
+        .summary
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("#{edition}"));
+    let mut body = format!(
+        "<p><a href=\"/\">&larr; Back to gallery</a></p>\n<h1>{}</h1>\n",
+        escape_html(&title)
+    );
+    if let Some(url) = &nft.summary.image_url {
+        body.push_str(&format!("<img class=\"full\" src=\"{url}\">\n"));
+    }
+    if let Some(err) = &nft.summary.error {
+        body.push_str(&format!(
+            "<p class=\"error\">Metadata couldn't be read: {}</p>\n",
+            escape_html(err)
+        ));
+    }
+    if let Some(metadata) = &nft.metadata {
+        if let Some(description) = metadata["description"].as_str() {
+            body.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+        }
+        if let Some(attributes) = metadata["attributes"].as_array() {
+            body.push_str("<table>\n");
+            for attribute in attributes {
+                body.push_str(&format!(
+                    "<tr><th>{}</th><td>{}</td></tr>\n",
+                    escape_html(&value_text(&attribute["trait_type"])),
+                    escape_html(&value_text(&attribute["value"]))
+                ));
+            }
+            body.push_str("</table>\n");
+        }
+    }
+    if let Some(url) = &nft.summary.metadata_url {
+        body.push_str(&format!("<p><a href=\"{url}\">Raw metadata</a></p>\n"));
+    }
+
+    Ok(Html(page(&title, &body)).into_response())
+}
+
+async fn image_file(
+    State(root): State<Root>,
+    UrlPath(file): UrlPath<String>,
+) -> Result<Response, ServeError> {
+    serve_file(&root.dir.join(IMAGES_DIR), &file).await
+}
+
+async fn metadata_file(
+    State(root): State<Root>,
+    UrlPath(file): UrlPath<String>,
+) -> Result<Response, ServeError> {
+    serve_file(&root.dir.join(METADATA_DIR), &file).await
+}
+
+/// Files at the top of the output directory, such as manifests
+async fn root_file(
+    State(root): State<Root>,
+    UrlPath(file): UrlPath<String>,
+) -> Result<Response, ServeError> {
+    serve_file(&root.dir, &file).await
+}
+
+async fn serve_file(dir: &Path, file: &str) -> Result<Response, ServeError> {
+    // Only plain file names are served, so requests can't escape the output directory
+    if Path::new(file).file_name().and_then(|name| name.to_str()) != Some(file)
+        || file.starts_with('.')
+    {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    let path = dir.join(file);
+    if !path.is_file() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    };
+    let data = fs::read(&path).await?;
+    Ok(([(header::CONTENT_TYPE, content_type)], data).into_response())
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; background: #111; color: #eee; }}
+a {{ color: #9cf; }}
+.grid {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(160px, 1fr)); gap: 1em; }}
+.card {{ display: flex; flex-direction: column; align-items: center; text-decoration: none; }}
+.card img, .missing {{ width: 160px; height: 160px; object-fit: contain; background: #222; }}
+.missing {{ display: flex; align-items: center; justify-content: center; }}
+img.full {{ max-width: 100%; max-height: 70vh; image-rendering: pixelated; }}
+th {{ text-align: left; padding-right: 1em; }}
+.error {{ color: #f88; font-size: 0.8em; }}
+</style>
+</head>
+<body>
+{body}
+</body>
+</html>
+"#,
+        escape_html(title)
+    )
+}
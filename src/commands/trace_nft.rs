@@ -0,0 +1,45 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::chia::nft::trace_nft;
+use recovery_tools::{encode_puzzle_hash, launcher_id_from_string};
+use tracing::info;
+
+#[derive(Args)]
+#[command(about = "Follows NFTs forward to their current coin and owner")]
+pub struct TraceNFT {
+    /// The NFT ID or launcher ID to trace. Can be passed multiple times
+    #[arg(short, long, required = true)]
+    nft_id: Vec<String>,
+}
+
+impl TraceNFT {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let client = ctx.client()?;
+
+        for nft_id in &self.nft_id {
+            info!(%nft_id, "Tracing NFT");
+            let launcher_id = launcher_id_from_string(nft_id)?;
+            let ownership = trace_nft(&client, &launcher_id).await?;
+
+            let owner_address = encode_puzzle_hash(&ownership.p2_puzzle_hash, "xch")?;
+            let owner_did = match ownership.owner_did {
+                Some(did) => encode_puzzle_hash(&did, "did:chia:")?,
+                None => String::from("none"),
+            };
+            output(format!(
+                "{nft_id} launcher_id={} current_coin={} confirmed_height={} owner_puzzle_hash={} \
+                 owner_address={owner_address} owner_did={owner_did} spends={} transfers={}",
+                ownership.launcher_id,
+                ownership.current_coin.coin.name(),
+                ownership.current_coin.confirmed_block_index,
+                ownership.p2_puzzle_hash,
+                ownership.spends,
+                ownership.transfers,
+            ));
+        }
+
+        anyhow::Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use recovery_tools::attestation::{Attestation, ATTESTATION_FILE};
+use recovery_tools::manifest::{Manifest, Mismatch};
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{info, warn};
+
+#[derive(Args)]
+#[command(about = "Checks an attestation's signature, and that the recovered files match it")]
+pub struct VerifyAttestation {
+    /// The directory containing the output-images and output-metadata directories [default: the output directory]
+    #[arg(short, long)]
+    dir: Option<PathBuf>,
+
+    /// The attestation to check [default: attestation.json in the directory]
+    #[arg(short, long)]
+    attestation: Option<PathBuf>,
+
+    /// The public key the attestation must be signed by, in hex, as published by the signer
+    #[arg(long)]
+    public_key: String,
+
+    /// Also check that every attested coin exists and was spent on chain. Requires the full node
+    #[arg(long)]
+    check_coins: bool,
+}
+
+impl VerifyAttestation {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let dir = ctx.dir_or(self.dir.as_ref());
+        let file = self
+            .attestation
+            .clone()
+            .unwrap_or_else(|| dir.join(ATTESTATION_FILE));
+        let attestation: Attestation = serde_json::from_slice(&fs::read(&file).await?)?;
+        let public_key = attestation.public_key()?;
+        info!(
+            file = %file.display(),
+            public_key = %attestation.public_key,
+            fingerprint = public_key.get_fingerprint(),
+            files = attestation.files.len(),
+            coins = attestation.coins.len(),
+            merkle_root = %attestation.merkle_root,
+            "Checking attestation"
+        );
+
+        if !attestation.verify()? {
+            anyhow::bail!(
+                "The signature doesn't match the attested files and coins, or the Merkle root isn't the root of the files"
+            );
+        }
+        info!("Signature is valid");
+        let expected = self.public_key.trim_start_matches("0x");
+        if !expected.eq_ignore_ascii_case(&attestation.public_key) {
+            anyhow::bail!(
+                "The attestation is signed by a different key, {}",
+                attestation.public_key
+            );
+        }
+        info!("Signed by the expected key");
+
+        let mismatches = Manifest::from_dir(dir)?.check(&attestation.manifest()?);
+        for mismatch in &mismatches {
+            match mismatch {
+                Mismatch::Missing(path) => warn!(file = %path, "Missing file"),
+                Mismatch::Different(path) => {
+                    warn!(file = %path, "File doesn't match the attestation")
+                }
+                Mismatch::Extra(path) => warn!(file = %path, "File isn't in the attestation"),
+            }
+        }
+        if !mismatches.is_empty() {
+            anyhow::bail!(
+                "The recovered files don't match the attestation, {} problems found",
+                mismatches.len()
+            );
+        }
+        info!(
+            files = attestation.files.len(),
+            "Every file matches the attestation"
+        );
+
+        if self.check_coins {
+            let client = ctx.client()?;
+            for coin in &attestation.coins {
+                let record = client
+                    .get_coin_record_by_name(coin)
+                    .await?
+                    .ok_or(anyhow!("Attested coin {coin} isn't on chain"))?;
+                if !record.spent {
+                    anyhow::bail!("Attested coin {coin} hasn't been spent, so holds no data");
+                }
+                info!(%coin, height = record.spent_block_index, "Coin is on chain");
+            }
+        }
+        output(format!(
+            "verified {} public_key={} merkle_root={} files={} coins_checked={}",
+            file.display(),
+            attestation.public_key,
+            attestation.merkle_root,
+            attestation.files.len(),
+            if self.check_coins { "yes" } else { "no" },
+        ));
+        anyhow::Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::Result;
+use clap::Args;
+use recovery_tools::merkle::{decode_hash, InclusionProof};
+use recovery_tools::sha256;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{info, warn};
+
+#[derive(Args)]
+#[command(about = "Checks that an image belongs to a collection, from its inclusion proof")]
+pub struct VerifyInclusion {
+    /// The inclusion proof, as written by prove-inclusion
+    proof: PathBuf,
+
+    /// The image to check. Its hash must be the one the proof is for
+    #[arg(long)]
+    image: Option<PathBuf>,
+
+    /// The collection's published Merkle root, in hex, that the proof must lead to
+    #[arg(long)]
+    root: String,
+}
+
+impl VerifyInclusion {
+    pub async fn execute(&self, _ctx: &Context) -> Result<()> {
+        let proof: InclusionProof = serde_json::from_slice(&fs::read(&self.proof).await?)?;
+        info!(
+            file = %proof.path,
+            index = proof.index,
+            leaves = proof.leaves,
+            merkle_root = %proof.root,
+            "Checking inclusion proof"
+        );
+
+        if !proof.verify()? {
+            anyhow::bail!("The proof doesn't lead from the file's hash to its Merkle root");
+        }
+        info!("Proof leads to its Merkle root");
+
+        if decode_hash(&self.root)? != decode_hash(&proof.root)? {
+            anyhow::bail!("The proof is for a different Merkle root, {}", proof.root);
+        }
+        info!("Merkle root is the published one");
+
+        match &self.image {
+            Some(image) => {
+                if sha256(&fs::read(image).await?) != decode_hash(&proof.file_hash)? {
+                    anyhow::bail!("{} isn't the file the proof is for", image.display());
+                }
+                info!(image = %image.display(), "Image is the file the proof is for");
+            }
+            None => warn!("No --image given, so only the proof itself was checked"),
+        }
+        output(format!(
+            "verified {} merkle_root={} image_checked={}",
+            proof.path,
+            proof.root,
+            if self.image.is_some() { "yes" } else { "no" },
+        ));
+        anyhow::Ok(())
+    }
+}
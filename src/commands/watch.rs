@@ -0,0 +1,455 @@
+use crate::commands::recover_metadata::write_metadata_documents;
+use crate::commands::scan::write_atomically;
+use crate::config::Context;
+use crate::logging::output;
+use anyhow::{anyhow, Result};
+use clap::Args;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use futures::StreamExt;
+use recovery_tools::archive::{image_filename, IMAGES_DIR, METADATA_DIR};
+use recovery_tools::assemble::{CollectionAssembler, ImageAssembler, MetadataAssembler};
+use recovery_tools::chia::coins::conditions_for_coin;
+use recovery_tools::chia::memo::parse_memos_from_conditions;
+use recovery_tools::chia::metadata::decode_metadata;
+use recovery_tools::chia::Recoverer;
+use recovery_tools::{Framing, Marker};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::time::Duration;
+use tokio::fs;
+use tracing::{debug, info, warn};
+
+const STATE_FILE: &str = "watch-state.json";
+
+#[derive(Args)]
+#[command(about = "Follows the chain tip and recovers new inscriptions as they're completed")]
+pub struct Watch {
+    /// The block height to start from when there's no saved state [default: the current peak]
+    #[arg(long)]
+    from_height: Option<u32>,
+
+    /// How many blocks behind the peak to stay, so blocks that are reorganized away aren't read
+    #[arg(long, default_value_t = 3)]
+    confirmations: u32,
+
+    /// Seconds to wait between checks for new blocks
+    #[arg(long, default_value_t = 30)]
+    interval: u64,
+
+    /// How many blocks an unfinished inscription can go without a new spend along its chain before
+    /// it's given up on. The default is about a week
+    #[arg(long, default_value_t = 32256)]
+    abandon_after: u32,
+
+    /// The file the last processed height and unfinished inscriptions are saved to, so watching
+    /// resumes where it stopped [default: watch-state.json in the output directory]
+    #[arg(long)]
+    state: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Collection,
+    Metadata,
+    Image,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Collection => "collection",
+            Kind::Metadata => "metadata",
+            Kind::Image => "image",
+        }
+    }
+}
+
+/// An inscription found on chain that hasn't been recovered in full yet
+#[derive(Clone, Serialize, Deserialize)]
+struct Inscription {
+    kind: Kind,
+    /// The coin whose spend starts the inscription
+    coin: Bytes32,
+    height: u32,
+    /// The number of files written so far
+    files: usize,
+    /// The height the last coin read along its chain was spent at. Missing from older state files,
+    /// where the height the inscription was found at stands in for it
+    #[serde(default)]
+    last_read: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct WatchState {
+    last_height: Option<u32>,
+    pending: Vec<Inscription>,
+    /// The coins read along chains, by the height they were spent at. Chains are read up to the
+    /// peak, ahead of the blocks checked, so an image can be found after the collection it belongs
+    /// to has been read past it. Coins are kept until their block has been checked
+    #[serde(default)]
+    walked: HashMap<Bytes32, u32>,
+}
+
+/// How far an inscription's chain has been read, kept between checks so each one only reads the
+/// new coins. Lost on restart, when the chain is read again from the start
+struct Progress {
+    assembly: Assembly,
+    /// The last coin read, which the next read resumes after
+    last_coin: Option<Bytes32>,
+    images: usize,
+}
+
+enum Assembly {
+    Collection(CollectionAssembler),
+    Metadata(MetadataAssembler),
+    Image(ImageAssembler),
+}
+
+impl Progress {
+    fn new(kind: Kind, framing: &Framing) -> Self {
+        let assembly = match kind {
+            Kind::Collection => {
+                Assembly::Collection(CollectionAssembler::new().with_framing(framing.clone()))
+            }
+            Kind::Metadata => {
+                Assembly::Metadata(MetadataAssembler::new().with_framing(framing.clone()))
+            }
+            Kind::Image => Assembly::Image(ImageAssembler::new().with_framing(framing.clone())),
+        };
+        Self {
+            assembly,
+            last_coin: None,
+            images: 0,
+        }
+    }
+}
+
+enum Outcome {
+    Complete,
+    /// The chain hasn't reached the end of the inscription yet
+    Pending,
+    /// The data can't be assembled, so reading more of the chain won't help
+    Broken(anyhow::Error),
+}
+
+impl Watch {
+    pub async fn execute(&self, ctx: &Context) -> Result<()> {
+        let state_file = self
+            .state
+            .clone()
+            .unwrap_or_else(|| ctx.output_dir.join(STATE_FILE));
+        let state: WatchState = match fs::read(&state_file).await {
+            Ok(contents) => serde_json::from_slice(&contents)?,
+            Err(_) => WatchState::default(),
+        };
+        if let Some(last_height) = state.last_height {
+            info!(last_height, pending = state.pending.len(), "Resuming watch");
+        }
+        if let Some(dir) = state_file.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        info!(
+            output = %ctx.output_dir.display(),
+            state = %state_file.display(),
+            "Watching for inscriptions"
+        );
+
+        let mut watcher = Watcher {
+            recoverer: ctx.recoverer()?,
+            state_file,
+            state,
+            progress: HashMap::new(),
+        };
+        loop {
+            // Everything found is saved as it's found, so a failed check is picked up by the next
+            if let Err(err) = watcher.check(self, ctx).await {
+                warn!(error = %format!("{err:#}"), "Check failed, retrying");
+            }
+            tokio::time::sleep(Duration::from_secs(self.interval)).await;
+        }
+    }
+}
+
+struct Watcher {
+    recoverer: Recoverer,
+    state_file: PathBuf,
+    state: WatchState,
+    progress: HashMap<Bytes32, Progress>,
+}
+
+impl Watcher {
+    /// Checks the blocks confirmed since the last check for new inscriptions, then reads further
+    /// along the chains of the pending ones
+    async fn check(&mut self, watch: &Watch, ctx: &Context) -> Result<()> {
+        let client = self.recoverer.client();
+        let blockchain = client
+            .get_blockchain_state()
+            .await
+            .map_err(|err| anyhow!("Unable to get the blockchain state: {err}"))?;
+        let peak = match blockchain.peak {
+            Some(peak) if blockchain.sync.synced => peak.height,
+            _ => {
+                warn!("The node isn't synced, waiting");
+                return Ok(());
+            }
+        };
+        let to_height = peak.saturating_sub(watch.confirmations);
+        let from_height = match self.state.last_height {
+            Some(last_height) => last_height + 1,
+            None => watch.from_height.unwrap_or(to_height),
+        };
+        if from_height > to_height {
+            return Ok(());
+        }
+
+        for height in from_height..=to_height {
+            for (kind, coin) in find_inscriptions(client, height, ctx).await? {
+                info!(?kind, %coin, height, "Found inscription");
+                self.state.pending.push(Inscription {
+                    kind,
+                    coin,
+                    height,
+                    files: 0,
+                    last_read: height,
+                });
+            }
+            self.state.last_height = Some(height);
+            self.save().await?;
+        }
+
+        // Images are read once the collections have been, since most belong to one
+        let abandon_before = to_height.saturating_sub(watch.abandon_after);
+        self.advance(ctx, false, abandon_before).await?;
+        self.advance(ctx, true, abandon_before).await?;
+        // Images in the blocks checked have been compared with the coins read, so coins spent in
+        // those blocks won't be needed again
+        self.state
+            .walked
+            .retain(|_coin, height| *height > to_height);
+        self.save().await?;
+        debug!(
+            height = to_height,
+            pending = self.state.pending.len(),
+            "Waiting for new blocks"
+        );
+        Ok(())
+    }
+
+    /// Reads further along the chains of the pending collections and metadata, or of the pending
+    /// images, writing what's been completed and dropping finished inscriptions. Images whose coin
+    /// was read as part of another inscription are dropped too, as are inscriptions whose chain
+    /// hasn't been spent since before `abandon_before`
+    async fn advance(&mut self, ctx: &Context, images: bool, abandon_before: u32) -> Result<()> {
+        let mut index = 0;
+        while index < self.state.pending.len() {
+            let inscription = &mut self.state.pending[index];
+            if (inscription.kind == Kind::Image) != images {
+                index += 1;
+                continue;
+            }
+            if images && self.state.walked.contains_key(&inscription.coin) {
+                debug!(coin = %inscription.coin, "Image is part of a collection");
+                self.state.pending.remove(index);
+                continue;
+            }
+
+            let coin = inscription.coin;
+            let dir = ctx.output_dir.join(coin.to_string());
+            let progress = self
+                .progress
+                .entry(coin)
+                .or_insert_with(|| Progress::new(inscription.kind, self.recoverer.framing()));
+            let outcome = match read_chain(
+                &self.recoverer,
+                inscription,
+                progress,
+                &dir,
+                &mut self.state.walked,
+            )
+            .await
+            {
+                Ok(outcome) => outcome,
+                Err(err) => {
+                    // The assembler may have taken a memo that the last coin read doesn't cover
+                    // yet, so the chain is read again from the start on the next check
+                    self.progress.remove(&coin);
+                    return Err(err);
+                }
+            };
+            match outcome {
+                Outcome::Pending
+                    if inscription.last_read.max(inscription.height) >= abandon_before =>
+                {
+                    index += 1;
+                    continue;
+                }
+                Outcome::Pending => warn!(
+                    kind = ?inscription.kind,
+                    coin = %inscription.coin,
+                    files = inscription.files,
+                    last_read = inscription.last_read,
+                    "Giving up on abandoned inscription"
+                ),
+                Outcome::Complete => output(format!(
+                    "recovered {} {} files={} dir={}",
+                    inscription.kind.name(),
+                    inscription.coin,
+                    inscription.files,
+                    dir.display()
+                )),
+                Outcome::Broken(err) => warn!(
+                    kind = ?inscription.kind,
+                    coin = %inscription.coin,
+                    error = %err,
+                    "Giving up on inscription"
+                ),
+            }
+            self.progress.remove(&coin);
+            self.state.pending.remove(index);
+        }
+        Ok(())
+    }
+
+    async fn save(&self) -> Result<()> {
+        write_atomically(&self.state_file, &serde_json::to_vec_pretty(&self.state)?).await
+    }
+}
+
+/// Finds the spends in the block at `height` whose memos start an inscription
+async fn find_inscriptions(
+    client: &FullnodeClient,
+    height: u32,
+    ctx: &Context,
+) -> Result<Vec<(Kind, Bytes32)>> {
+    let block = client.get_block_record_by_height(height).await?;
+    // Only transaction blocks have spends
+    if block.timestamp.is_none() {
+        return Ok(vec![]);
+    }
+    debug!(height, "Checking block");
+    let (_additions, removals) = client
+        .get_additions_and_removals(&block.header_hash)
+        .await?;
+
+    let mut found = vec![];
+    for removal in removals {
+        let memo = conditions_for_coin(client, &removal)
+            .await
+            .and_then(|conditions| parse_memos_from_conditions(conditions, ctx.memo_strategy));
+        let memo = match memo {
+            Ok(Some(memo)) => memo,
+            Ok(None) => continue,
+            Err(err) => {
+                warn!(coin = %removal.coin.name(), height, error = %err, "Skipping coin");
+                continue;
+            }
+        };
+        let markers: Vec<Marker> = ctx
+            .framing
+            .find_markers(&memo)
+            .into_iter()
+            .map(|found| found.marker)
+            .collect();
+        // A collection starts with an image, so its own start marker decides what it is
+        let kind = if markers.contains(&Marker::CollectionStart) {
+            Kind::Collection
+        } else if markers.contains(&Marker::MetaStart) {
+            Kind::Metadata
+        } else if markers.contains(&Marker::PngStart) {
+            Kind::Image
+        } else {
+            continue;
+        };
+        found.push((kind, removal.coin.name()));
+    }
+    Ok(found)
+}
+
+/// Reads the inscription's chain from where it was last left, writing each file as it's completed
+async fn read_chain(
+    recoverer: &Recoverer,
+    inscription: &mut Inscription,
+    progress: &mut Progress,
+    dir: &Path,
+    walked: &mut HashMap<Bytes32, u32>,
+) -> Result<Outcome> {
+    let (start, skip) = match progress.last_coin {
+        Some(coin) => (coin, 1),
+        None => (inscription.coin, 0),
+    };
+    let mut links = pin!(recoverer.walk_chain(&start).skip(skip));
+    while let Some(link) = links.next().await {
+        // Errors reading the chain are the node's, so they end the watch rather than the inscription
+        let link = link?;
+        let coin = link.coin.coin.name();
+        walked.insert(coin, link.coin.spent_block_index);
+        inscription.last_read = inscription.last_read.max(link.coin.spent_block_index);
+        let memo = match link.require_memo() {
+            Ok(memo) => memo,
+            Err(err) => return Ok(Outcome::Broken(err)),
+        };
+
+        match &mut progress.assembly {
+            Assembly::Collection(assembler) => match assembler.push(memo) {
+                Ok(Some(image)) => {
+                    progress.images += 1;
+                    let index = progress.images;
+                    // Files written before a restart are already there
+                    if index > inscription.files {
+                        let filename = image_filename(image.filename.as_deref(), || {
+                            format!("{index}-{}.png", inscription.coin)
+                        });
+                        write_file(&dir.join(IMAGES_DIR), &filename, &image.data).await?;
+                        inscription.files = index;
+                    }
+                    if assembler.is_finished() {
+                        return Ok(Outcome::Complete);
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => return Ok(Outcome::Broken(err)),
+            },
+            Assembly::Image(assembler) => match assembler.push(memo) {
+                Ok(Some(image)) => {
+                    let filename = image_filename(image.filename.as_deref(), || {
+                        format!("{}.png", inscription.coin)
+                    });
+                    write_file(&dir.join(IMAGES_DIR), &filename, &image.data).await?;
+                    inscription.files = 1;
+                    return Ok(Outcome::Complete);
+                }
+                Ok(None) => {}
+                Err(err) => return Ok(Outcome::Broken(err)),
+            },
+            Assembly::Metadata(assembler) => match assembler.push(memo) {
+                Ok(Some(payload)) => {
+                    let all_meta = match decode_metadata(&payload) {
+                        Ok(all_meta) => all_meta,
+                        Err(err) => return Ok(Outcome::Broken(err)),
+                    };
+                    inscription.files =
+                        write_metadata_documents(&all_meta, &dir.join(METADATA_DIR)).await?;
+                    return Ok(Outcome::Complete);
+                }
+                Ok(None) => {}
+                Err(err) => return Ok(Outcome::Broken(err)),
+            },
+        }
+        progress.last_coin = Some(coin);
+    }
+    Ok(Outcome::Pending)
+}
+
+async fn write_file(dir: &Path, filename: &str, data: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir).await?;
+    let file = dir.join(filename);
+    fs::write(&file, data).await?;
+    debug!(bytes = data.len(), "Wrote image");
+    output(format!("wrote {}", file.display()));
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Context as _, Result};
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use recovery_tools::chia::cache::SpendCache;
+use recovery_tools::chia::client::{get_chia_client, NodeConfig};
+use recovery_tools::chia::memo::MemoStrategy;
+use recovery_tools::chia::Recoverer;
+use recovery_tools::known::{find_known, KnownCollection, KNOWN_COLLECTIONS};
+use recovery_tools::Framing;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The config file, `~/.config/recovery-tools/config.toml` unless `--config` is passed
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub node: NodeConfig,
+    /// Where recovered files are written and read back from. Defaults to the working directory
+    pub output_dir: Option<PathBuf>,
+    /// Where spent coins read from the node are cached. Defaults to `recovery-tools` in the
+    /// platform's cache directory, such as `~/.cache/recovery-tools`
+    pub cache_dir: Option<PathBuf>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named collection, so its coins don't have to be passed on every command
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// The coin at the start of the collection's images
+    pub start_coin: Option<String>,
+    /// The coin at the start of the collection's metadata
+    pub metadata_coin: Option<String>,
+    pub memo_strategy: Option<String>,
+    pub markers: MarkersConfig,
+}
+
+impl Profile {
+    fn from_known(known: &KnownCollection) -> Self {
+        Self {
+            start_coin: Some(known.start_coin.to_string()),
+            metadata_coin: known.metadata_coin.map(String::from),
+            memo_strategy: Some(known.memo_strategy.to_string()),
+            markers: MarkersConfig::default(),
+        }
+    }
+}
+
+/// Markers that differ from the Chia Gods ones. Any left out keep the Chia Gods value
+#[derive(Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkersConfig {
+    pub collection_start: Option<String>,
+    pub collection_end: Option<String>,
+    pub meta_start: Option<String>,
+    pub meta_end: Option<String>,
+}
+
+impl MarkersConfig {
+    fn framing(&self) -> Framing {
+        let marker = |value: &Option<String>, default: Cow<'static, [u8]>| {
+            value
+                .as_ref()
+                .map_or(default, |value| Cow::Owned(value.as_bytes().to_vec()))
+        };
+        let defaults = Framing::default();
+        Framing {
+            collection_start: marker(&self.collection_start, defaults.collection_start),
+            collection_end: marker(&self.collection_end, defaults.collection_end),
+            meta_start: marker(&self.meta_start, defaults.meta_start),
+            meta_end: marker(&self.meta_end, defaults.meta_end),
+        }
+    }
+}
+
+/// The options given on the command line that override the config file
+pub struct Overrides {
+    pub profile: Option<String>,
+    pub port: Option<u16>,
+    pub memo_strategy: Option<MemoStrategy>,
+    pub output_dir: Option<PathBuf>,
+    /// Read everything from the node, without the cache
+    pub no_cache: bool,
+}
+
+impl Config {
+    /// Reads the config file at `path`, or the default one if it exists
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read or parsed, or if `path` is given but doesn't exist.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match dirs_next::config_dir() {
+                Some(dir) if dir.join("recovery-tools/config.toml").is_file() => {
+                    dir.join("recovery-tools/config.toml")
+                }
+                _ => return Ok(Self::default()),
+            },
+        };
+        debug!(file = %path.display(), "Reading config");
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read config {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Unable to parse config {}", path.display()))
+    }
+
+    /// Settles every setting, preferring the command line, then the profile, then the config file
+    ///
+    /// # Errors
+    ///
+    /// Fails if the profile doesn't exist, or its memo strategy isn't valid.
+    pub fn resolve(self, overrides: Overrides) -> Result<Context> {
+        // A profile in the config file takes precedence over a known collection of the same name
+        let mut framing = None;
+        let profile = match overrides.profile {
+            Some(name) => match (self.profiles.get(&name), find_known(&name)) {
+                (Some(profile), _) => Some((name, profile.clone())),
+                (None, Some(known)) => {
+                    framing = Some(known.framing.clone());
+                    Some((name, Profile::from_known(known)))
+                }
+                (None, None) => {
+                    let names: Vec<&str> = self
+                        .profiles
+                        .keys()
+                        .map(String::as_str)
+                        .chain(KNOWN_COLLECTIONS.iter().map(|known| known.name))
+                        .collect();
+                    anyhow::bail!(
+                        "No profile or known collection named {name}, the profiles are: {}",
+                        names.join(", ")
+                    );
+                }
+            },
+            None => None,
+        };
+
+        let profile_strategy = profile
+            .as_ref()
+            .and_then(|(_name, profile)| profile.memo_strategy.as_deref())
+            .map(str::parse)
+            .transpose()?;
+        let mut node = self.node;
+        if let Some(port) = overrides.port {
+            node.port = port;
+        }
+
+        Ok(Context {
+            node,
+            memo_strategy: overrides
+                .memo_strategy
+                .or(profile_strategy)
+                .unwrap_or_default(),
+            framing: framing
+                .or_else(|| {
+                    profile
+                        .as_ref()
+                        .map(|(_name, profile)| profile.markers.framing())
+                })
+                .unwrap_or_default(),
+            output_dir: overrides
+                .output_dir
+                .or(self.output_dir)
+                .unwrap_or_else(|| PathBuf::from(".")),
+            cache_dir: if overrides.no_cache {
+                None
+            } else {
+                self.cache_dir
+                    .or_else(|| dirs_next::cache_dir().map(|dir| dir.join("recovery-tools")))
+            },
+            profile,
+        })
+    }
+}
+
+/// Everything a command needs beyond its own arguments
+pub struct Context {
+    pub node: NodeConfig,
+    pub memo_strategy: MemoStrategy,
+    pub framing: Framing,
+    pub output_dir: PathBuf,
+    /// Where spent coins are cached, unless caching is turned off
+    pub cache_dir: Option<PathBuf>,
+    /// The profile picked with `--profile`, and its name
+    pub profile: Option<(String, Profile)>,
+}
+
+impl Context {
+    /// # Errors
+    ///
+    /// Fails if the node's Chia root can't be found.
+    pub fn client(&self) -> Result<FullnodeClient> {
+        get_chia_client(&self.node)
+    }
+
+    /// # Errors
+    ///
+    /// Fails if the node's Chia root can't be found.
+    pub fn recoverer(&self) -> Result<Recoverer> {
+        let recoverer =
+            Recoverer::new(self.client()?, self.memo_strategy).with_framing(self.framing.clone());
+        Ok(self.cached(recoverer))
+    }
+
+    /// Has `recoverer` use the cache, unless caching is turned off
+    pub fn cached(&self, recoverer: Recoverer) -> Recoverer {
+        match &self.cache_dir {
+            Some(dir) => recoverer.with_cache(SpendCache::new(dir.clone())),
+            None => recoverer,
+        }
+    }
+
+    /// The directory given on the command line, or else the output directory
+    pub fn dir_or<'a>(&'a self, dir: Option<&'a PathBuf>) -> &'a Path {
+        dir.map_or(&self.output_dir, PathBuf::as_path)
+    }
+
+    /// The start coin given on the command line, or else the profile's
+    ///
+    /// # Errors
+    ///
+    /// Fails if neither has one.
+    pub fn start_coin(&self, coin: Option<&String>) -> Result<String> {
+        self.coin(coin, "start_coin", |profile| profile.start_coin.as_ref())
+    }
+
+    /// The metadata coin given on the command line, or else the profile's
+    ///
+    /// # Errors
+    ///
+    /// Fails if neither has one.
+    pub fn metadata_coin(&self, coin: Option<&String>) -> Result<String> {
+        self.coin(coin, "metadata_coin", |profile| {
+            profile.metadata_coin.as_ref()
+        })
+    }
+
+    fn coin(
+        &self,
+        coin: Option<&String>,
+        key: &str,
+        from_profile: impl Fn(&Profile) -> Option<&String>,
+    ) -> Result<String> {
+        if let Some(coin) = coin {
+            return Ok(coin.clone());
+        }
+        match &self.profile {
+            Some((name, profile)) => from_profile(profile)
+                .cloned()
+                .ok_or(anyhow!("Profile {name} has no {key}, pass --coin")),
+            None => anyhow::bail!("Pass --coin, or a --profile with a {key}"),
+        }
+    }
+}
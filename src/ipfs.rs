@@ -0,0 +1,494 @@
+//! Offline IPFS content addressing: `UnixFS` file and directory DAGs, CIDs and `CARv1` files.
+//!
+//! Files are chunked with fixed size chunks stored as raw leaves and assembled with the balanced
+//! layout, the same as `ipfs add --cid-version 1`. The chunk size and number of links per node
+//! can be changed to match other tools (e.g. `ipfs-car` uses 1 MiB chunks and 1024 links).
+
+use crate::sha256;
+use std::collections::HashSet;
+use std::fmt;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 262_144;
+pub const DEFAULT_MAX_LINKS: usize = 174;
+
+const CODEC_RAW: u64 = 0x55;
+const CODEC_DAG_PB: u64 = 0x70;
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_FILE: u64 = 2;
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cid {
+    pub codec: u64,
+    /// sha2-256 digest of the block
+    pub digest: [u8; 32],
+}
+
+impl Cid {
+    fn for_block(codec: u64, block: &[u8]) -> Self {
+        Self {
+            codec,
+            digest: sha256(block),
+        }
+    }
+
+    /// The binary CIDv1: version, codec and multihash
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, MULTIHASH_SHA2_256);
+        write_varint(&mut bytes, 32);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+
+    /// Parses a CIDv0 (`Qm...`) or base32 CIDv1 (`b...`) string.
+    /// Only sha2-256 CIDs are supported, since those are the only ones we produce.
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        if text.len() == 46 && text.starts_with("Qm") {
+            let multihash = base58_decode(text)?;
+            if multihash.len() != 34 || multihash[0] != 0x12 || multihash[1] != 0x20 {
+                return None;
+            }
+            return Some(Self {
+                codec: CODEC_DAG_PB,
+                digest: multihash[2..].try_into().ok()?,
+            });
+        }
+
+        let bytes = base32_decode(text.strip_prefix('b')?)?;
+        let mut reader = &bytes[..];
+        if read_varint(&mut reader)? != 1 {
+            return None;
+        }
+        let codec = read_varint(&mut reader)?;
+        if read_varint(&mut reader)? != MULTIHASH_SHA2_256 || read_varint(&mut reader)? != 32 {
+            return None;
+        }
+        Some(Self {
+            codec,
+            digest: reader.try_into().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b{}", base32_encode(&self.to_bytes()))
+    }
+}
+
+/// A node that was added to the DAG, and the information its parent needs to link to it
+#[derive(Clone, Debug)]
+pub struct DagNode {
+    pub cid: Cid,
+    /// Size of the node plus everything it links to
+    pub cumulative_size: u64,
+}
+
+struct FileNode {
+    node: DagNode,
+    file_size: u64,
+}
+
+/// Builds `UnixFS` DAGs and collects their blocks so they can be written to a CAR file
+pub struct DagBuilder {
+    chunk_size: usize,
+    max_links: usize,
+    blocks: Vec<(Cid, Vec<u8>)>,
+    seen: HashSet<Cid>,
+}
+
+impl DagBuilder {
+    #[must_use]
+    pub fn new(chunk_size: usize, max_links: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            max_links: max_links.max(2),
+            blocks: vec![],
+            seen: HashSet::new(),
+        }
+    }
+
+    fn add_block(&mut self, codec: u64, block: Vec<u8>) -> Cid {
+        let cid = Cid::for_block(codec, &block);
+        if self.seen.insert(cid.clone()) {
+            self.blocks.push((cid.clone(), block));
+        }
+        cid
+    }
+
+    /// Adds a file and returns its root node
+    pub fn add_file(&mut self, data: &[u8]) -> DagNode {
+        let mut level: Vec<FileNode> = data
+            .chunks(self.chunk_size)
+            .map(|chunk| FileNode {
+                node: DagNode {
+                    cid: self.add_block(CODEC_RAW, chunk.to_vec()),
+                    cumulative_size: chunk.len() as u64,
+                },
+                file_size: chunk.len() as u64,
+            })
+            .collect();
+        if level.is_empty() {
+            level.push(FileNode {
+                node: DagNode {
+                    cid: self.add_block(CODEC_RAW, vec![]),
+                    cumulative_size: 0,
+                },
+                file_size: 0,
+            });
+        }
+
+        while level.len() > 1 {
+            let max_links = self.max_links;
+            level = level
+                .chunks(max_links)
+                .map(|children| self.add_file_node(children))
+                .collect();
+        }
+        level.remove(0).node
+    }
+
+    fn add_file_node(&mut self, children: &[FileNode]) -> FileNode {
+        let file_size: u64 = children.iter().map(|child| child.file_size).sum();
+
+        let mut unixfs = vec![];
+        write_varint_field(&mut unixfs, 1, UNIXFS_FILE);
+        write_varint_field(&mut unixfs, 3, file_size);
+        for child in children {
+            write_varint_field(&mut unixfs, 4, child.file_size);
+        }
+
+        let links: Vec<(&str, &DagNode)> = children.iter().map(|child| ("", &child.node)).collect();
+        let node = self.add_pb_node(&links, &unixfs);
+        FileNode { node, file_size }
+    }
+
+    /// Adds a directory of already added entries and returns its node
+    pub fn add_directory(&mut self, entries: &[(String, DagNode)]) -> DagNode {
+        let mut sorted: Vec<&(String, DagNode)> = entries.iter().collect();
+        sorted.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+        let mut unixfs = vec![];
+        write_varint_field(&mut unixfs, 1, UNIXFS_DIRECTORY);
+
+        let links: Vec<(&str, &DagNode)> = sorted
+            .iter()
+            .map(|(name, node)| (name.as_str(), node))
+            .collect();
+        self.add_pb_node(&links, &unixfs)
+    }
+
+    fn add_pb_node(&mut self, links: &[(&str, &DagNode)], data: &[u8]) -> DagNode {
+        // dag-pb requires links to be encoded before data
+        let mut node = vec![];
+        for (name, child) in links {
+            let mut link = vec![];
+            write_bytes_field(&mut link, 1, &child.cid.to_bytes());
+            write_bytes_field(&mut link, 2, name.as_bytes());
+            write_varint_field(&mut link, 3, child.cumulative_size);
+            write_bytes_field(&mut node, 2, &link);
+        }
+        write_bytes_field(&mut node, 1, data);
+
+        let links_size: u64 = links.iter().map(|(_, child)| child.cumulative_size).sum();
+        let cumulative_size = node.len() as u64 + links_size;
+        DagNode {
+            cid: self.add_block(CODEC_DAG_PB, node),
+            cumulative_size,
+        }
+    }
+
+    /// Serializes every added block into a `CARv1` file with the given root
+    #[must_use]
+    pub fn to_car(&self, root: &Cid) -> Vec<u8> {
+        let root_bytes = root.to_bytes();
+
+        // dag-cbor header: {"roots": [root], "version": 1}
+        let mut header = vec![0xA2, 0x65];
+        header.extend_from_slice(b"roots");
+        header.push(0x81);
+        // tag 42 (CID), byte string holding the identity multibase prefix and the CID
+        header.extend_from_slice(&[0xD8, 0x2A]);
+        write_cbor_bytes_header(&mut header, root_bytes.len() + 1);
+        header.push(0x00);
+        header.extend_from_slice(&root_bytes);
+        header.push(0x67);
+        header.extend_from_slice(b"version");
+        header.push(0x01);
+
+        let mut car = vec![];
+        write_varint(&mut car, header.len() as u64);
+        car.extend_from_slice(&header);
+        for (cid, block) in &self.blocks {
+            let cid_bytes = cid.to_bytes();
+            write_varint(&mut car, (cid_bytes.len() + block.len()) as u64);
+            car.extend_from_slice(&cid_bytes);
+            car.extend_from_slice(block);
+        }
+        car
+    }
+}
+
+/// Pulls the CID and the path after it out of an `ipfs://` URI or an HTTP gateway URL
+/// (`https://gateway/ipfs/<cid>/path` or `https://<cid>.ipfs.gateway/path`)
+#[must_use]
+pub fn cid_from_uri(uri: &str) -> Option<(Cid, String)> {
+    let split_path = |rest: &str| -> Option<(Cid, String)> {
+        let (cid, path) = rest.split_once('/').unwrap_or((rest, ""));
+        Some((Cid::parse(cid)?, path.to_string()))
+    };
+
+    if let Some(rest) = uri.strip_prefix("ipfs://") {
+        return split_path(rest.strip_prefix("ipfs/").unwrap_or(rest));
+    }
+
+    let without_scheme = uri.split_once("://").map_or(uri, |(_scheme, rest)| rest);
+    let (host, path) = without_scheme
+        .split_once('/')
+        .unwrap_or((without_scheme, ""));
+    if let Some(rest) = path.strip_prefix("ipfs/") {
+        return split_path(rest);
+    }
+    if let Some((cid, _gateway)) = host.split_once(".ipfs.") {
+        return Some((Cid::parse(cid)?, path.to_string()));
+    }
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(reader: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = reader.split_first()?;
+        *reader = rest;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(out, field << 3);
+    write_varint(out, value);
+}
+
+fn write_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(out, (field << 3) | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_cbor_bytes_header(out: &mut Vec<u8>, len: usize) {
+    match len {
+        0..=23 => out.push(0x40 | len as u8),
+        24..=0xFF => out.extend_from_slice(&[0x58, len as u8]),
+        _ => {
+            out.push(0x59);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+    }
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1F) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize] as char);
+    }
+    out
+}
+
+fn base32_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_lowercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = vec![];
+    for c in text.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)?;
+        for byte in out.iter_mut().rev() {
+            carry += usize::from(*byte) * 58;
+            *byte = (carry & 0xFF) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            out.insert(0, (carry & 0xFF) as u8);
+            carry >>= 8;
+        }
+    }
+    let leading_zeros = text.bytes().take_while(|&c| c == b'1').count();
+    let mut decoded = vec![0; leading_zeros];
+    decoded.extend(out);
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `printf 'hello world\n' | ipfs add --cid-version 1`
+    const HELLO_WORLD: &str = "bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4";
+    /// `ipfs add --cid-version 1` of an empty file
+    const EMPTY_FILE: &str = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+    /// `ipfs object new unixfs-dir`, as a CIDv1 and as the CIDv0 kubo prints by default
+    const EMPTY_DIRECTORY: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+    const EMPTY_DIRECTORY_V0: &str = "QmUNLLsPACCz1vLxQVkXqqLX5R1X345qqfHbsf67hvA3Nn";
+
+    fn builder() -> DagBuilder {
+        DagBuilder::new(DEFAULT_CHUNK_SIZE, DEFAULT_MAX_LINKS)
+    }
+
+    fn block(builder: &DagBuilder, cid: &Cid) -> Vec<u8> {
+        builder
+            .blocks
+            .iter()
+            .find(|(block_cid, _)| block_cid == cid)
+            .map(|(_, block)| block.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn small_file_matches_kubo() {
+        let mut builder = builder();
+        assert_eq!(
+            builder.add_file(b"hello world\n").cid.to_string(),
+            HELLO_WORLD
+        );
+        assert_eq!(builder.add_file(b"").cid.to_string(), EMPTY_FILE);
+    }
+
+    #[test]
+    fn empty_directory_matches_kubo() {
+        let cid = builder().add_directory(&[]).cid;
+        assert_eq!(cid.to_string(), EMPTY_DIRECTORY);
+        assert_eq!(Cid::parse(EMPTY_DIRECTORY_V0), Some(cid));
+    }
+
+    #[test]
+    fn multi_chunk_file_links_raw_leaves() {
+        // Two full chunks and a partial one, under a single root
+        let data: Vec<u8> = (0..600_000u32).map(|i| (i % 251) as u8).collect();
+        let mut builder = builder();
+        let root = builder.add_file(&data);
+
+        let mut expected = vec![];
+        for chunk in data.chunks(DEFAULT_CHUNK_SIZE) {
+            let leaf = Cid::for_block(CODEC_RAW, chunk);
+            assert_eq!(block(&builder, &leaf), chunk);
+            // PBLink: Hash, an empty Name, Tsize
+            expected.extend_from_slice(&[0x12, 0x2C, 0x0A, 0x24, 0x01, 0x55, 0x12, 0x20]);
+            expected.extend_from_slice(&leaf.digest);
+            expected.extend_from_slice(&[0x12, 0x00, 0x18]);
+            if chunk.len() == DEFAULT_CHUNK_SIZE {
+                expected.extend_from_slice(&[0x80, 0x80, 0x10]);
+            } else {
+                // 75712
+                expected.extend_from_slice(&[0xC0, 0xCF, 0x04]);
+            }
+        }
+        // PBNode Data: a UnixFS file of 600000 bytes, with the size of each block
+        expected.extend_from_slice(&[0x0A, 0x12, 0x08, 0x02, 0x18, 0xC0, 0xCF, 0x24]);
+        expected.extend_from_slice(&[0x20, 0x80, 0x80, 0x10, 0x20, 0x80, 0x80, 0x10]);
+        expected.extend_from_slice(&[0x20, 0xC0, 0xCF, 0x04]);
+
+        assert_eq!(root.cid.codec, CODEC_DAG_PB);
+        assert_eq!(block(&builder, &root.cid), expected);
+        assert_eq!(
+            root.cumulative_size,
+            expected.len() as u64 + data.len() as u64
+        );
+    }
+
+    #[test]
+    fn file_over_max_links_gets_another_level() {
+        let mut builder = DagBuilder::new(1, 2);
+        let root = builder.add_file(b"abc");
+        // Three raw leaves, a node over "a" and "b", a node over "c", and the root over both
+        assert_eq!(builder.blocks.len(), 6);
+        let root_block = block(&builder, &root.cid);
+        assert!(root_block.ends_with(&[0x0A, 0x08, 0x08, 0x02, 0x18, 0x03, 0x20, 0x02, 0x20, 0x01]));
+    }
+
+    #[test]
+    fn directory_links_entries_by_name() {
+        let mut builder = builder();
+        let hello = builder.add_file(b"hello world\n");
+        let empty = builder.add_directory(&[]);
+        let root = builder.add_directory(&[
+            (String::from("hello.txt"), hello),
+            (String::from("empty"), empty),
+        ]);
+
+        let empty = Cid::parse(EMPTY_DIRECTORY).unwrap();
+        let hello = Cid::parse(HELLO_WORLD).unwrap();
+        let mut expected = vec![0x12, 0x2F, 0x0A, 0x24, 0x01, 0x70, 0x12, 0x20];
+        expected.extend_from_slice(&empty.digest);
+        expected.extend_from_slice(&[0x12, 0x05]);
+        expected.extend_from_slice(b"empty");
+        expected.extend_from_slice(&[0x18, 0x04]);
+        expected.extend_from_slice(&[0x12, 0x33, 0x0A, 0x24, 0x01, 0x55, 0x12, 0x20]);
+        expected.extend_from_slice(&hello.digest);
+        expected.extend_from_slice(&[0x12, 0x09]);
+        expected.extend_from_slice(b"hello.txt");
+        expected.extend_from_slice(&[0x18, 0x0C]);
+        // PBNode Data: a UnixFS directory
+        expected.extend_from_slice(&[0x0A, 0x02, 0x08, 0x01]);
+
+        assert_eq!(block(&builder, &root.cid), expected);
+        assert_eq!(root.cumulative_size, expected.len() as u64 + 4 + 12);
+    }
+
+    #[test]
+    fn parses_gateway_uris() {
+        let (cid, path) = cid_from_uri(&format!("ipfs://{HELLO_WORLD}/a/b.png")).unwrap();
+        assert_eq!(cid.to_string(), HELLO_WORLD);
+        assert_eq!(path, "a/b.png");
+        let (cid, path) =
+            cid_from_uri(&format!("https://ipfs.io/ipfs/{EMPTY_DIRECTORY_V0}/x")).unwrap();
+        assert_eq!(cid.to_string(), EMPTY_DIRECTORY);
+        assert_eq!(path, "x");
+        let (cid, _) = cid_from_uri(&format!("https://{EMPTY_DIRECTORY}.ipfs.dweb.link/")).unwrap();
+        assert_eq!(cid.to_string(), EMPTY_DIRECTORY);
+    }
+}
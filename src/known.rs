@@ -0,0 +1,60 @@
+//! Collections known to be inscribed on chain, so they can be recovered and checked by name.
+
+use crate::chia::memo::MemoStrategy;
+use crate::manifest::Manifest;
+use crate::Framing;
+use anyhow::Result;
+
+/// A collection inscribed on chain
+pub struct KnownCollection {
+    /// The short name the collection is recovered by
+    pub name: &'static str,
+    pub title: &'static str,
+    /// The coin at the start of the collection's images
+    pub start_coin: &'static str,
+    /// The coin at the start of the collection's metadata
+    pub metadata_coin: Option<&'static str>,
+    pub memo_strategy: MemoStrategy,
+    pub framing: Framing,
+    /// The number of images in the collection, when known
+    pub images: Option<u64>,
+    /// The expected hashes of the recovered files, in `sha256sum` format, when known
+    pub manifest: Option<&'static str>,
+}
+
+/// The registry of known collections
+///
+/// The image count and expected hashes of a collection are only filled in from a recovery that
+/// has been checked against an independent copy of the collection, such as the files hosted when
+/// it was minted. `recover-known` logs both for a collection that doesn't have them yet: set
+/// `images` to the count it logs, and `manifest` to the `manifest.sha256` it writes, read with
+/// `include_str!` from a file next to this one. Chia Gods hasn't been checked like this yet.
+pub const KNOWN_COLLECTIONS: &[KnownCollection] = &[KnownCollection {
+    name: "chia-gods",
+    title: "Chia Gods",
+    start_coin: "8c0793fece985be90444fa6f01f40861047b3b2307053f378ec72f5a5c4bb4d7",
+    metadata_coin: Some("e743335b56ec7428790ba164fe1f130dc7b4bdf32ee16da6f1a09621c27a326c"),
+    memo_strategy: MemoStrategy::FirstMemo,
+    framing: Framing::CHIA_GODS,
+    images: None,
+    manifest: None,
+}];
+
+/// Looks up a known collection by name
+#[must_use]
+pub fn find_known(name: &str) -> Option<&'static KnownCollection> {
+    KNOWN_COLLECTIONS
+        .iter()
+        .find(|known| known.name.eq_ignore_ascii_case(name))
+}
+
+impl KnownCollection {
+    /// The expected hashes of the recovered files
+    ///
+    /// # Errors
+    ///
+    /// Fails if the built-in manifest can't be parsed.
+    pub fn expected_manifest(&self) -> Result<Option<Manifest>> {
+        self.manifest.map(Manifest::parse).transpose()
+    }
+}
@@ -1,10 +1,26 @@
+use ::chia::protocol::Bytes;
+use ::chia::sha2::Sha256;
 use anyhow::Result;
-use chia::protocol::Bytes;
 use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
 use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::io::Read;
 use std::str::from_utf8;
 
+pub mod archive;
+pub mod assemble;
+pub mod attestation;
+pub mod chia;
+pub mod ipfs;
+pub mod known;
+pub mod manifest;
+pub mod merkle;
+pub mod payload;
+pub mod phash;
+pub mod png;
+pub mod thumbnails;
+
 pub const PNG_START: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
 const I_END_CHUNK: [u8; 12] = [
     0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
@@ -14,6 +30,66 @@ const END_COLLECTION: &[u8; 11] = b"CHIAGODSEND";
 const START_META: &[u8; 17] = b"CHIAGODSMETASTART";
 const END_META: &[u8; 15] = b"CHIAGODSMETAEND";
 
+/// The framing markers used to delimit data inscribed in memos
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Marker {
+    CollectionStart,
+    CollectionEnd,
+    MetaStart,
+    MetaEnd,
+    PngStart,
+    PngEnd,
+}
+
+impl Marker {
+    pub const ALL: [Marker; 6] = [
+        Marker::CollectionStart,
+        Marker::CollectionEnd,
+        Marker::MetaStart,
+        Marker::MetaEnd,
+        Marker::PngStart,
+        Marker::PngEnd,
+    ];
+
+    #[must_use]
+    pub fn bytes(self) -> &'static [u8] {
+        match self {
+            Marker::CollectionStart => &START_COLLECTION[..],
+            Marker::CollectionEnd => &END_COLLECTION[..],
+            Marker::MetaStart => &START_META[..],
+            Marker::MetaEnd => &END_META[..],
+            Marker::PngStart => &PNG_START[..],
+            Marker::PngEnd => &I_END_CHUNK[..],
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Marker::CollectionStart => "collection_start",
+            Marker::CollectionEnd => "collection_end",
+            Marker::MetaStart => "meta_start",
+            Marker::MetaEnd => "meta_end",
+            Marker::PngStart => "png_start",
+            Marker::PngEnd => "png_end",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkerMatch {
+    pub marker: Marker,
+    /// Byte offset of the start of the marker within the memo
+    pub offset: usize,
+}
+
+/// Finds the first occurrence of every Chia Gods marker in a memo, ordered by offset
+#[must_use]
+pub fn find_markers(memo: &[u8]) -> Vec<MarkerMatch> {
+    Framing::CHIA_GODS.find_markers(memo)
+}
+
 fn bytes_contains(haystack: &[u8], needle: &[u8]) -> Option<(usize, usize)> {
     haystack
         .windows(needle.len())
@@ -21,9 +97,141 @@ fn bytes_contains(haystack: &[u8], needle: &[u8]) -> Option<(usize, usize)> {
         .map(|start| (start, start + needle.len()))
 }
 
+/// The markers a collection's creator used to frame its data on chain. Images are always framed
+/// by the PNG signature and `IEND` chunk, so only the collection and metadata markers vary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framing {
+    pub collection_start: Cow<'static, [u8]>,
+    pub collection_end: Cow<'static, [u8]>,
+    pub meta_start: Cow<'static, [u8]>,
+    pub meta_end: Cow<'static, [u8]>,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Self::CHIA_GODS
+    }
+}
+
+impl Framing {
+    /// The markers of the Chia Gods collection
+    pub const CHIA_GODS: Framing = Framing {
+        collection_start: Cow::Borrowed(START_COLLECTION),
+        collection_end: Cow::Borrowed(END_COLLECTION),
+        meta_start: Cow::Borrowed(START_META),
+        meta_end: Cow::Borrowed(END_META),
+    };
+
+    #[must_use]
+    pub fn marker_bytes(&self, marker: Marker) -> &[u8] {
+        match marker {
+            Marker::CollectionStart => &self.collection_start,
+            Marker::CollectionEnd => &self.collection_end,
+            Marker::MetaStart => &self.meta_start,
+            Marker::MetaEnd => &self.meta_end,
+            Marker::PngStart | Marker::PngEnd => marker.bytes(),
+        }
+    }
+
+    /// Finds the first occurrence of every marker in a memo, ordered by offset
+    #[must_use]
+    pub fn find_markers(&self, memo: &[u8]) -> Vec<MarkerMatch> {
+        let mut matches: Vec<MarkerMatch> = Marker::ALL
+            .into_iter()
+            .filter_map(|marker| {
+                bytes_contains(memo, self.marker_bytes(marker))
+                    .map(|(offset, _end)| MarkerMatch { marker, offset })
+            })
+            .collect();
+        matches.sort_by_key(|found| found.offset);
+        matches
+    }
+
+    #[must_use]
+    pub fn is_meta(&self, memo: &Bytes) -> bool {
+        bytes_contains(memo, &self.meta_start).is_some()
+    }
+
+    #[must_use]
+    pub fn is_meta_end(&self, memo: &Bytes) -> bool {
+        bytes_contains(memo, &self.meta_end).is_some()
+    }
+
+    #[must_use]
+    pub fn is_collection_start(&self, memo: &Bytes) -> bool {
+        bytes_contains(memo, &self.collection_start).is_some()
+    }
+
+    #[must_use]
+    pub fn is_collection_end(&self, memo: &Bytes) -> bool {
+        bytes_contains(memo, &self.collection_end).is_some()
+    }
+
+    #[must_use]
+    pub fn filter_collection_start(&self, memo: &Bytes) -> Bytes {
+        // If we encounter the collection start we should also strip everything else before it
+        if let Some((_start, end)) = bytes_contains(memo, &self.collection_start) {
+            return Bytes::new(memo[end..].to_vec());
+        }
+
+        memo.clone()
+    }
+
+    #[must_use]
+    pub fn filter_collection_end(&self, memo: &Bytes) -> Bytes {
+        // If we encounter the collection end we should also strip everything else after it
+        if let Some((start, _end)) = bytes_contains(memo, &self.collection_end) {
+            return Bytes::new(memo[..start].to_vec());
+        }
+
+        memo.clone()
+    }
+
+    /// Strips everything before and including the metadata start marker from a memo
+    #[must_use]
+    pub fn filter_meta_start(&self, memo: &Bytes) -> Bytes {
+        if let Some((_start, end)) = bytes_contains(memo, &self.meta_start) {
+            return Bytes::new(memo[end..].to_vec());
+        }
+
+        memo.clone()
+    }
+
+    /// Strips everything after and including the metadata end marker from a memo
+    #[must_use]
+    pub fn filter_meta_end(&self, memo: &Bytes) -> Bytes {
+        if let Some((start, _end)) = bytes_contains(memo, &self.meta_end) {
+            return Bytes::new(memo[..start].to_vec());
+        }
+
+        memo.clone()
+    }
+
+    /// The filename inscribed after the end of an image, if the memo has one
+    #[must_use]
+    pub fn get_filename(&self, memo: &Bytes) -> Option<String> {
+        // if the filename exists, it exists after I_END_CHUNK
+        // If the collection end marker also exists, it will be immediately after filename
+        // First, we can just strip out the collection end
+        let working_memo = self.filter_collection_end(memo);
+        if let Some((_start, end)) = bytes_contains(&working_memo, &I_END_CHUNK[..]) {
+            if let Ok(stringfile) = from_utf8(&working_memo[end..]) {
+                return Some(String::from(stringfile));
+            }
+        }
+
+        None
+    }
+}
+
 #[must_use]
 pub fn is_meta(memo: &Bytes) -> bool {
-    bytes_contains(memo, START_META).is_some()
+    Framing::CHIA_GODS.is_meta(memo)
+}
+
+#[must_use]
+pub fn is_meta_end(memo: &Bytes) -> bool {
+    Framing::CHIA_GODS.is_meta_end(memo)
 }
 
 #[must_use]
@@ -38,12 +246,12 @@ pub fn is_png_end(memo: &Bytes) -> bool {
 
 #[must_use]
 pub fn is_collection_start(memo: &Bytes) -> bool {
-    bytes_contains(memo, &START_COLLECTION[..]).is_some()
+    Framing::CHIA_GODS.is_collection_start(memo)
 }
 
 #[must_use]
 pub fn is_collection_end(memo: &Bytes) -> bool {
-    bytes_contains(memo, &END_COLLECTION[..]).is_some()
+    Framing::CHIA_GODS.is_collection_end(memo)
 }
 
 #[must_use]
@@ -68,22 +276,12 @@ pub fn filter_png_end(memo: &Bytes) -> Bytes {
 
 #[must_use]
 pub fn filter_collection_start(memo: &Bytes) -> Bytes {
-    // If we encounter START_COLLECTION we should also strip everything else before it
-    if let Some((_start, end)) = bytes_contains(memo, &START_COLLECTION[..]) {
-        return Bytes::new(memo[end..].to_vec());
-    }
-
-    memo.clone()
+    Framing::CHIA_GODS.filter_collection_start(memo)
 }
 
 #[must_use]
 pub fn filter_collection_end(memo: &Bytes) -> Bytes {
-    // If we encounter END_COLLECTION we should also strip everything else before it
-    if let Some((start, _end)) = bytes_contains(memo, &END_COLLECTION[..]) {
-        return Bytes::new(memo[..start].to_vec());
-    }
-
-    memo.clone()
+    Framing::CHIA_GODS.filter_collection_end(memo)
 }
 
 /// Strips everything before and including the `START_META` marker from a memo.
@@ -99,12 +297,7 @@ pub fn filter_collection_end(memo: &Bytes) -> Bytes {
 /// inspecting its return value will have no effect.
 #[must_use]
 pub fn filter_meta_start(memo: &Bytes) -> Bytes {
-    // If we encounter START_META we should also strip everything else before it
-    if let Some((_start, end)) = bytes_contains(memo, &START_META[..]) {
-        return Bytes::new(memo[end..].to_vec());
-    }
-
-    memo.clone()
+    Framing::CHIA_GODS.filter_meta_start(memo)
 }
 
 /// Strips everything after (and including) the `END_META` marker from a memo.
@@ -120,27 +313,19 @@ pub fn filter_meta_start(memo: &Bytes) -> Bytes {
 /// inspecting its return value will have no effect.
 #[must_use]
 pub fn filter_meta_end(memo: &Bytes) -> Bytes {
-    // If we encounter END_META we should also strip everything else before it
-    if let Some((start, _end)) = bytes_contains(memo, &END_META[..]) {
-        return Bytes::new(memo[..start].to_vec());
-    }
-
-    memo.clone()
+    Framing::CHIA_GODS.filter_meta_end(memo)
 }
 
 #[must_use]
 pub fn get_filename(memo: &Bytes) -> Option<String> {
-    // if the filename exists, it exists after I_END_CHUNK
-    // If the collection end marker also exists, it will be immediately after filename
-    // First, we can just strip out the collection end
-    let working_memo = filter_collection_end(memo);
-    if let Some((_start, end)) = bytes_contains(&working_memo, &I_END_CHUNK[..]) {
-        if let Ok(stringfile) = from_utf8(&working_memo[end..]) {
-            return Some(String::from(stringfile));
-        }
-    }
+    Framing::CHIA_GODS.get_filename(memo)
+}
 
-    None
+#[must_use]
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize()
 }
 
 /// Decompresses a gzip-compressed byte slice into raw bytes.
@@ -175,3 +360,126 @@ pub fn coin_id_from_string(coin_id_str: &str) -> Result<Bytes32> {
     let coinid = hex::decode(stripped_coin_id_str)?;
     Ok(Bytes32::new(&coinid))
 }
+
+/// Decodes an NFT ID (`nft1...`) or a hex launcher ID into the launcher coin ID.
+///
+/// # Errors
+///
+/// Will return `Err` if the bech32m or hex decode fails, the prefix is not exactly `nft`, or the
+/// decoded ID is not 32 bytes
+pub fn launcher_id_from_string(nft_id: &str) -> Result<Bytes32> {
+    if !nft_id.to_lowercase().starts_with("nft1") {
+        return coin_id_from_string(nft_id);
+    }
+    let launcher_id = decode_bech32m(nft_id, &["nft"])?;
+    if launcher_id.len() != 32 {
+        anyhow::bail!("NFT ID does not decode to a 32 byte launcher ID");
+    }
+    Ok(Bytes32::new(&launcher_id))
+}
+
+/// Decodes an address (`xch1...`) or a hex puzzle hash into the puzzle hash.
+///
+/// # Errors
+///
+/// Will return `Err` if the bech32m or hex decode fails, the prefix is not exactly `xch` or
+/// `txch`, or the decoded hash is not 32 bytes
+pub fn puzzle_hash_from_string(address: &str) -> Result<Bytes32> {
+    let lowercase = address.to_lowercase();
+    if !lowercase.starts_with("xch1") && !lowercase.starts_with("txch1") {
+        return coin_id_from_string(address);
+    }
+    let puzzle_hash = decode_bech32m(address, &["xch", "txch"])?;
+    if puzzle_hash.len() != 32 {
+        anyhow::bail!("Address does not decode to a 32 byte puzzle hash");
+    }
+    Ok(Bytes32::new(&puzzle_hash))
+}
+
+/// Decodes a bech32m string whose prefix is one of `hrps`. Chia only uses bech32m, so a string
+/// with a plain bech32 checksum is rejected
+fn decode_bech32m(text: &str, hrps: &[&str]) -> Result<Vec<u8>> {
+    let decoded = bech32::primitives::decode::CheckedHrpstring::new::<bech32::Bech32m>(text)?;
+    let hrp = decoded.hrp().to_lowercase();
+    if !hrps.contains(&hrp.as_str()) {
+        anyhow::bail!("Unexpected prefix {hrp}, expected {}", hrps.join(" or "));
+    }
+    Ok(decoded.byte_iter().collect())
+}
+
+/// Encodes a puzzle hash as a bech32m string with the given prefix (e.g. `xch` or `did:chia:`).
+///
+/// # Errors
+///
+/// Will return `Err` if the prefix is not a valid bech32 human readable part
+pub fn encode_puzzle_hash(puzzle_hash: &Bytes32, prefix: &str) -> Result<String> {
+    let hrp = bech32::Hrp::parse(prefix)?;
+    Ok(bech32::encode::<bech32::Bech32m>(
+        hrp,
+        puzzle_hash.as_slice(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<Ck: bech32::Checksum>(hrp: &str, data: &[u8]) -> String {
+        bech32::encode::<Ck>(bech32::Hrp::parse(hrp).unwrap(), data).unwrap()
+    }
+
+    #[test]
+    fn launcher_id() {
+        let id = Bytes32::new(&[5; 32]);
+        let nft_id = encode_puzzle_hash(&id, "nft").unwrap();
+        assert_eq!(launcher_id_from_string(&nft_id).unwrap(), id);
+        assert_eq!(launcher_id_from_string(&nft_id.to_uppercase()).unwrap(), id);
+        assert_eq!(launcher_id_from_string(&hex::encode([5; 32])).unwrap(), id);
+    }
+
+    #[test]
+    fn launcher_id_rejects_other_prefixes() {
+        for hrp in ["nft1x", "nft1", "xch"] {
+            let text = encode::<bech32::Bech32m>(hrp, &[5; 32]);
+            assert!(launcher_id_from_string(&text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn launcher_id_rejects_bech32() {
+        let text = encode::<bech32::Bech32>("nft", &[5; 32]);
+        assert!(launcher_id_from_string(&text).is_err());
+    }
+
+    #[test]
+    fn launcher_id_rejects_other_lengths() {
+        let text = encode::<bech32::Bech32m>("nft", &[5; 31]);
+        assert!(launcher_id_from_string(&text).is_err());
+    }
+
+    #[test]
+    fn puzzle_hash() {
+        let hash = Bytes32::new(&[9; 32]);
+        for hrp in ["xch", "txch"] {
+            let address = encode_puzzle_hash(&hash, hrp).unwrap();
+            assert_eq!(puzzle_hash_from_string(&address).unwrap(), hash);
+        }
+        assert_eq!(
+            puzzle_hash_from_string(&format!("0x{}", hex::encode([9; 32]))).unwrap(),
+            hash
+        );
+    }
+
+    #[test]
+    fn puzzle_hash_rejects_other_prefixes_and_bech32() {
+        for text in [
+            encode::<bech32::Bech32m>("xch1x", &[9; 32]),
+            encode::<bech32::Bech32m>("txch1", &[9; 32]),
+            encode::<bech32::Bech32m>("nft", &[9; 32]),
+            encode::<bech32::Bech32>("xch", &[9; 32]),
+            encode::<bech32::Bech32m>("xch", &[9; 20]),
+        ] {
+            assert!(puzzle_hash_from_string(&text).is_err(), "{text}");
+        }
+    }
+}
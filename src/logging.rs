@@ -0,0 +1,155 @@
+use clap::ValueEnum;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use recovery_tools::chia::{RecoveredImage, RecoveryObserver};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+/// All progress bars are drawn through this, so log lines can be printed above them
+static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Set while a full screen interface owns the terminal, so log lines don't draw over it
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Sets up logging for the verbosity flags. `RUST_LOG` overrides the flags when it is set
+pub fn init(verbose: u8, quiet: u8, format: LogFormat) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        i16::MIN..=-2 => "error",
+        -1 => "warn",
+        0 => "info",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,recovery_tools={level}")));
+
+    // Progress bars are only drawn for people watching a terminal
+    let progress = MultiProgress::new();
+    if quiet > 0 || matches!(format, LogFormat::Json) {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    let _ = PROGRESS.set(progress);
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| LogWriter);
+    match format {
+        LogFormat::Text => builder
+            .without_time()
+            .with_target(verbose > 0)
+            .with_ansi(io::stderr().is_terminal())
+            .init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+/// Stops or resumes writing log lines. Lines logged while muted are dropped
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+}
+
+/// Prints a line of a command's results to stdout. Unlike log lines, results are printed whatever
+/// the verbosity, so `-qq` leaves only the results
+pub fn output(line: impl fmt::Display) {
+    progress().suspend(|| println!("{line}"));
+}
+
+fn progress() -> &'static MultiProgress {
+    PROGRESS.get_or_init(MultiProgress::new)
+}
+
+/// Writes log lines to stderr, clearing any progress bars while it does so. Stdout is left to
+/// the results, so they can be piped into other tools
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if MUTED.load(Ordering::Relaxed) {
+            return Ok(buf.len());
+        }
+        progress().suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Progress of a recovery: coins walked, bytes recovered, and images done
+pub struct RecoveryProgress {
+    bar: ProgressBar,
+    coins: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl RecoveryProgress {
+    /// Starts a progress bar. The time remaining is only estimated when the number of images is known
+    pub fn new(images: Option<u64>) -> Self {
+        let (bar, template) = match images {
+            Some(images) => (
+                ProgressBar::new(images),
+                "{spinner} [{elapsed_precise}] {bar:30} {pos}/{len} images, {msg} (ETA {eta})",
+            ),
+            None => (
+                ProgressBar::no_length(),
+                "{spinner} [{elapsed_precise}] {pos} images, {msg}",
+            ),
+        };
+        let bar = progress().add(bar);
+        bar.set_style(ProgressStyle::with_template(template).expect("valid progress template"));
+        bar.enable_steady_tick(Duration::from_millis(200));
+
+        let progress = Self {
+            bar,
+            coins: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+        };
+        progress.update_message();
+        progress
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish();
+    }
+
+    fn update_message(&self) {
+        self.bar.set_message(format!(
+            "{} coins walked, {} recovered",
+            self.coins.load(Ordering::Relaxed),
+            HumanBytes(self.bytes.load(Ordering::Relaxed))
+        ));
+    }
+}
+
+impl RecoveryObserver for RecoveryProgress {
+    fn coin_walked(&self, _coin_id: &Bytes32, bytes: usize) {
+        self.coins.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.update_message();
+    }
+
+    fn image_recovered(&self, _image: &RecoveredImage) {
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for RecoveryProgress {
+    fn drop(&mut self) {
+        if !self.bar.is_finished() {
+            self.bar.abandon();
+        }
+    }
+}
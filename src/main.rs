@@ -1,8 +1,15 @@
 use anyhow::Result;
 use clap::Parser;
 
-mod chia;
 mod commands;
+mod config;
+mod logging;
+
+use crate::config::{Config, Overrides};
+use crate::logging::LogFormat;
+use clap::ArgAction;
+use recovery_tools::chia::memo::MemoStrategy;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "Chia Gods Recovery Tools")]
@@ -10,9 +17,41 @@ mod commands;
 #[command(author = "Chris Marslender; Patrick Maslana")]
 #[command(about = "Recover images, collections, and metadata")]
 struct Cli {
-    /// The port for the Chia full node RPC
-    #[arg(short, long, default_value = "8555", global = true)]
-    port: u16,
+    /// The config file to read, instead of ~/.config/recovery-tools/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// The collection profile from the config file to use
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// The port for the Chia full node RPC [default: 8555]
+    #[arg(short, long, global = true)]
+    port: Option<u16>,
+
+    /// Which memos or conditions of each spend hold the data: first, all, output:<index>, remark [default: first]
+    #[arg(long, global = true)]
+    memo_strategy: Option<MemoStrategy>,
+
+    /// Where recovered files are written and read back from [default: the working directory]
+    #[arg(long, global = true)]
+    output_dir: Option<PathBuf>,
+
+    /// Read everything from the node, instead of the cache of spent coins
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Log more detail. Pass twice for trace output
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log less. Pass twice to only log errors
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    quiet: u8,
+
+    /// The format of log output
+    #[arg(long, value_enum, default_value_t, global = true)]
+    log_format: LogFormat,
 
     #[command(subcommand)]
     command: Commands,
@@ -24,16 +63,66 @@ enum Commands {
     RecoverImage(commands::recover_image::RecoverImage),
     RecoverCollection(commands::recover_collection::RecoverCollection),
     RecoverMetadata(commands::recover_metadata::RecoverMetadata),
+    TraceNFT(commands::trace_nft::TraceNFT),
+    Scan(commands::scan::Scan),
+    FindInscriptions(commands::find_inscriptions::FindInscriptions),
+    Serve(commands::serve::Serve),
+    ExportCar(commands::export_car::ExportCar),
+    Compare(commands::compare::Compare),
+    InspectCoin(commands::inspect_coin::InspectCoin),
+    DumpChain(commands::dump_chain::DumpChain),
+    Rebuild(commands::rebuild::Rebuild),
+    Report(commands::report::Report),
+    MatchImages(commands::match_images::MatchImages),
+    Known(commands::known::Known),
+    RecoverKnown(commands::recover_known::RecoverKnown),
+    Browse(commands::browse::Browse),
+    MintKit(commands::mint_kit::MintKit),
+    Attest(commands::attest::Attest),
+    VerifyAttestation(commands::verify_attestation::VerifyAttestation),
+    Manifest(commands::manifest::WriteManifest),
+    ProveInclusion(commands::prove_inclusion::ProveInclusion),
+    VerifyInclusion(commands::verify_inclusion::VerifyInclusion),
+    Watch(commands::watch::Watch),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    logging::init(cli.verbose, cli.quiet, cli.log_format);
+    let ctx = Config::load(cli.config.as_deref())?.resolve(Overrides {
+        profile: cli.profile,
+        port: cli.port,
+        memo_strategy: cli.memo_strategy,
+        output_dir: cli.output_dir,
+        no_cache: cli.no_cache,
+    })?;
 
     match cli.command {
-        Commands::LocateNFTData(cmd) => cmd.execute(cli.port).await,
-        Commands::RecoverImage(cmd) => cmd.execute(cli.port).await,
-        Commands::RecoverCollection(cmd) => cmd.execute(cli.port).await,
-        Commands::RecoverMetadata(cmd) => cmd.execute(cli.port).await,
+        Commands::LocateNFTData(cmd) => cmd.execute(&ctx).await,
+        Commands::RecoverImage(cmd) => cmd.execute(&ctx).await,
+        Commands::RecoverCollection(cmd) => cmd.execute(&ctx).await,
+        Commands::RecoverMetadata(cmd) => cmd.execute(&ctx).await,
+        Commands::TraceNFT(cmd) => cmd.execute(&ctx).await,
+        Commands::Scan(cmd) => cmd.execute(&ctx).await,
+        Commands::FindInscriptions(cmd) => cmd.execute(&ctx).await,
+        Commands::Serve(cmd) => cmd.execute(&ctx).await,
+        Commands::ExportCar(cmd) => cmd.execute(&ctx).await,
+        Commands::Compare(cmd) => cmd.execute(&ctx).await,
+        Commands::InspectCoin(cmd) => cmd.execute(&ctx).await,
+        Commands::DumpChain(cmd) => cmd.execute(&ctx).await,
+        Commands::Rebuild(cmd) => cmd.execute(&ctx).await,
+        Commands::Report(cmd) => cmd.execute(&ctx).await,
+        Commands::MatchImages(cmd) => cmd.execute(&ctx).await,
+        Commands::Known(cmd) => cmd.execute(&ctx).await,
+        Commands::RecoverKnown(cmd) => cmd.execute(&ctx).await,
+        Commands::Browse(cmd) => cmd.execute(&ctx).await,
+        Commands::MintKit(cmd) => cmd.execute(&ctx).await,
+        Commands::Attest(cmd) => cmd.execute(&ctx).await,
+        Commands::VerifyAttestation(cmd) => cmd.execute(&ctx).await,
+        Commands::Manifest(cmd) => cmd.execute(&ctx).await,
+        Commands::ProveInclusion(cmd) => cmd.execute(&ctx).await,
+        Commands::VerifyInclusion(cmd) => cmd.execute(&ctx).await,
+        Commands::Watch(cmd) => cmd.execute(&ctx).await,
     }
 }
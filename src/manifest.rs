@@ -0,0 +1,165 @@
+//! SHA-256 manifests of recovered files, in the format written by `sha256sum`.
+
+use crate::archive::{edition_from_filename, list_files, IMAGES_DIR, METADATA_DIR};
+use crate::merkle::{self, decode_hash, leaf_hash, InclusionProof};
+use crate::sha256;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+pub const MANIFEST_FILE: &str = "manifest.sha256";
+
+/// Starts the comment line that records the Merkle root, which `sha256sum` skips
+const MERKLE_ROOT_COMMENT: &str = "# merkle-root ";
+
+/// The hash of every file in a recovery, by path relative to the output directory
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub files: BTreeMap<String, [u8; 32]>,
+}
+
+/// A file that doesn't match the expected manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Missing(String),
+    Different(String),
+    /// A file that isn't in the expected manifest
+    Extra(String),
+}
+
+impl Manifest {
+    /// Hashes every file in the images and metadata directories under `root`
+    ///
+    /// # Errors
+    ///
+    /// Fails if a directory or file can't be read.
+    pub fn from_dir(root: &Path) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for dir in [IMAGES_DIR, METADATA_DIR] {
+            for path in list_files(&root.join(dir))? {
+                let name = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or(anyhow!("File name {} isn't UTF-8", path.display()))?;
+                files.insert(format!("{dir}/{name}"), sha256(&fs::read(&path)?));
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// Parses `sha256sum` output: a hex hash, a space, then the path, optionally marked binary with
+    /// a `*`. Blank lines and `#` comments are skipped, except the Merkle root, which is checked
+    ///
+    /// # Errors
+    ///
+    /// Fails if a line isn't a hash and a path, or the Merkle root isn't the root of the files.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        let mut stated_root = None;
+        for line in text.lines().map(str::trim) {
+            if let Some(root) = line.strip_prefix(MERKLE_ROOT_COMMENT) {
+                stated_root = Some(decode_hash(root)?);
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (hash, path) = line
+                .split_once(' ')
+                .ok_or(anyhow!("Manifest line has no path: {line}"))?;
+            let hash: [u8; 32] = hex::decode(hash)?
+                .try_into()
+                .map_err(|_| anyhow!("Manifest hash isn't 32 bytes: {line}"))?;
+            let path = path.trim_start();
+            let path = path.strip_prefix('*').unwrap_or(path);
+            files.insert(path.to_string(), hash);
+        }
+        let manifest = Self { files };
+        if stated_root.is_some_and(|root| root != manifest.merkle_root()) {
+            anyhow::bail!("Manifest's Merkle root isn't the root of its files");
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest in the format `sha256sum --check` reads, headed by the Merkle root
+    #[must_use]
+    pub fn to_sha256sum(&self) -> String {
+        let header = format!("{MERKLE_ROOT_COMMENT}{}\n", hex::encode(self.merkle_root()));
+        self.files.iter().fold(header, |mut output, (path, hash)| {
+            let _ = writeln!(output, "{}  {path}", hex::encode(hash));
+            output
+        })
+    }
+
+    /// The files of `expected` that are missing here or have a different hash, then the files
+    /// here that aren't in `expected`
+    #[must_use]
+    pub fn check(&self, expected: &Manifest) -> Vec<Mismatch> {
+        let changed = expected
+            .files
+            .iter()
+            .filter_map(|(path, hash)| match self.files.get(path) {
+                None => Some(Mismatch::Missing(path.clone())),
+                Some(actual) if actual != hash => Some(Mismatch::Different(path.clone())),
+                Some(_) => None,
+            });
+        let extra = self
+            .files
+            .keys()
+            .filter(|path| !expected.files.contains_key(*path))
+            .map(|path| Mismatch::Extra(path.clone()));
+        changed.chain(extra).collect()
+    }
+
+    /// The files in the order they're leaves of the Merkle tree: the images by edition number,
+    /// then the metadata by edition number. Files without an edition number follow the others in
+    /// their directory, by name
+    #[must_use]
+    pub fn merkle_leaves(&self) -> Vec<(&String, &[u8; 32])> {
+        let mut leaves: Vec<_> = self.files.iter().collect();
+        leaves.sort_by_key(|(path, _hash)| {
+            let (dir, name) = path.split_once('/').unwrap_or(("", path));
+            let group = [IMAGES_DIR, METADATA_DIR]
+                .iter()
+                .position(|known| *known == dir)
+                .unwrap_or(2);
+            let edition = edition_from_filename(Path::new(name)).unwrap_or(u32::MAX);
+            (group, edition, *path)
+        });
+        leaves
+    }
+
+    /// The root of the Merkle tree over the files
+    #[must_use]
+    pub fn merkle_root(&self) -> [u8; 32] {
+        merkle::root(&self.leaf_hashes())
+    }
+
+    /// A proof that the file at `path` is part of this manifest's Merkle tree, if it's listed
+    #[must_use]
+    pub fn inclusion_proof(&self, path: &str) -> Option<InclusionProof> {
+        let leaves = self.merkle_leaves();
+        let index = leaves.iter().position(|(leaf, _hash)| *leaf == path)?;
+        let hashes = self.leaf_hashes();
+        Some(InclusionProof {
+            path: path.to_string(),
+            file_hash: hex::encode(leaves[index].1),
+            index,
+            leaves: leaves.len(),
+            siblings: merkle::proof(&hashes, index)
+                .iter()
+                .map(hex::encode)
+                .collect(),
+            root: hex::encode(merkle::root(&hashes)),
+        })
+    }
+
+    fn leaf_hashes(&self) -> Vec<[u8; 32]> {
+        self.merkle_leaves()
+            .into_iter()
+            .map(|(_path, hash)| leaf_hash(hash))
+            .collect()
+    }
+}
@@ -0,0 +1,250 @@
+//! A Merkle tree over the files of a recovery, laid out as in RFC 6962, so a single file can be
+//! shown to belong to a collection without the rest of it.
+
+use crate::sha256;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// The hash of a leaf, from the SHA-256 of the file it stands for
+#[must_use]
+pub fn leaf_hash(file_hash: &[u8; 32]) -> [u8; 32] {
+    let mut data = vec![0];
+    data.extend_from_slice(file_hash);
+    sha256(&data)
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut data = vec![1];
+    data.extend_from_slice(left);
+    data.extend_from_slice(right);
+    sha256(&data)
+}
+
+/// The largest power of two smaller than `n`, where the tree over `n` leaves is split
+fn split(n: usize) -> usize {
+    1 << (n - 1).ilog2()
+}
+
+/// The root of the tree over `leaves`, which are leaf hashes. An empty tree's root is the hash of
+/// nothing
+#[must_use]
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    match leaves.len() {
+        0 => sha256(&[]),
+        1 => leaves[0],
+        n => {
+            let (left, right) = leaves.split_at(split(n));
+            node_hash(&root(left), &root(right))
+        }
+    }
+}
+
+/// The sibling hashes from the leaf at `index` up to the root, lowest first
+#[must_use]
+pub fn proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    if leaves.len() <= 1 {
+        return vec![];
+    }
+    let k = split(leaves.len());
+    let (left, right) = leaves.split_at(k);
+    let (mut path, sibling) = if index < k {
+        (proof(left, index), root(right))
+    } else {
+        (proof(right, index - k), root(left))
+    };
+    path.push(sibling);
+    path
+}
+
+/// Whether `siblings` lead from `leaf` at `index` of a tree of `size` leaves to `root`
+#[must_use]
+pub fn verify(
+    leaf: &[u8; 32],
+    index: usize,
+    size: usize,
+    siblings: &[[u8; 32]],
+    root: &[u8; 32],
+) -> bool {
+    if index >= size {
+        return false;
+    }
+    let (mut index, mut last) = (index, size - 1);
+    let mut hash = *leaf;
+    for sibling in siblings {
+        if last == 0 {
+            return false;
+        }
+        if index & 1 == 1 || index == last {
+            hash = node_hash(sibling, &hash);
+            while index & 1 == 0 && index != 0 {
+                index >>= 1;
+                last >>= 1;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        index >>= 1;
+        last >>= 1;
+    }
+    last == 0 && hash == *root
+}
+
+/// A proof that a file belongs to the collection with a given Merkle root
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// The file's path relative to the output directory
+    pub path: String,
+    /// The file's SHA-256, in hex
+    pub file_hash: String,
+    /// The file's position among the leaves
+    pub index: usize,
+    /// The number of leaves in the tree
+    pub leaves: usize,
+    /// The sibling hashes from the leaf to the root, in hex
+    pub siblings: Vec<String>,
+    /// The Merkle root of the collection, in hex
+    pub root: String,
+}
+
+impl InclusionProof {
+    /// Whether the proof leads from its file hash to its root
+    ///
+    /// # Errors
+    ///
+    /// Fails if a hash isn't 32 bytes of hex.
+    pub fn verify(&self) -> Result<bool> {
+        let siblings = self
+            .siblings
+            .iter()
+            .map(|sibling| decode_hash(sibling))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(verify(
+            &leaf_hash(&decode_hash(&self.file_hash)?),
+            self.index,
+            self.leaves,
+            &siblings,
+            &decode_hash(&self.root)?,
+        ))
+    }
+}
+
+/// Decodes a SHA-256 hash from hex
+///
+/// # Errors
+///
+/// Fails if the text isn't 32 bytes of hex.
+pub fn decode_hash(text: &str) -> Result<[u8; 32]> {
+    let text = text.trim();
+    hex::decode(text.strip_prefix("0x").unwrap_or(text))?
+        .try_into()
+        .map_err(|_| anyhow!("Hash {text} isn't 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The leaves and roots of the Merkle tree tests in RFC 6962's reference implementation,
+    /// certificate-transparency, where each tree holds the first n leaves
+    const CT_LEAVES: [&str; 8] = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ];
+    const CT_ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    fn ct_leaf_hash(data: &str) -> [u8; 32] {
+        let mut bytes = vec![0];
+        bytes.extend(hex::decode(data).unwrap());
+        sha256(&bytes)
+    }
+
+    fn leaves(n: usize) -> Vec<[u8; 32]> {
+        (0..n)
+            .map(|i| leaf_hash(&sha256(&i.to_be_bytes())))
+            .collect()
+    }
+
+    #[test]
+    fn empty_tree_root_is_hash_of_nothing() {
+        assert_eq!(
+            hex::encode(root(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn roots_match_reference_vectors() {
+        let hashes: Vec<_> = CT_LEAVES.iter().map(|leaf| ct_leaf_hash(leaf)).collect();
+        for (n, expected) in CT_ROOTS.iter().enumerate() {
+            assert_eq!(
+                hex::encode(root(&hashes[..=n])),
+                *expected,
+                "{} leaves",
+                n + 1
+            );
+        }
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        for size in 1..=17 {
+            let leaves = leaves(size);
+            let root = root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let siblings = proof(&leaves, index);
+                assert!(
+                    verify(leaf, index, size, &siblings, &root),
+                    "leaf {index} of {size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_proof_fails() {
+        for size in 2..=17 {
+            let leaves = leaves(size);
+            let root = root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let siblings = proof(&leaves, index);
+                for tampered in 0..siblings.len() {
+                    let mut siblings = siblings.clone();
+                    siblings[tampered][0] ^= 1;
+                    assert!(!verify(leaf, index, size, &siblings, &root));
+                }
+                for other in (0..size).filter(|other| *other != index) {
+                    assert!(!verify(leaf, other, size, &siblings, &root));
+                }
+                assert!(!verify(
+                    &leaves[(index + 1) % size],
+                    index,
+                    size,
+                    &siblings,
+                    &root
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn index_past_the_tree_fails() {
+        let leaves = leaves(4);
+        let root = root(&leaves);
+        assert!(!verify(&leaves[3], 4, 4, &proof(&leaves, 3), &root));
+    }
+}
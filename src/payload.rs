@@ -0,0 +1,211 @@
+use crate::{decompress_gzip_to_bytes, PNG_START};
+use base64::{engine::general_purpose, Engine};
+use chia::protocol::Bytes;
+use std::fmt;
+use std::str::from_utf8;
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const JPEG_START: [u8; 3] = [0xFF, 0xD8, 0xFF];
+const GIF_START: &[u8; 3] = b"GIF";
+const RIFF_START: &[u8; 4] = b"RIFF";
+const WEBP_MARKER: &[u8; 4] = b"WEBP";
+
+/// Upper bound on nested encodings, so a payload can't keep us decoding forever
+const MAX_DECODE_STEPS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadKind {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Json,
+    Text,
+    Binary,
+}
+
+impl PayloadKind {
+    #[must_use]
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&PNG_START) {
+            Self::Png
+        } else if data.starts_with(&JPEG_START) {
+            Self::Jpeg
+        } else if data.starts_with(GIF_START) {
+            Self::Gif
+        } else if data.len() >= 12 && data.starts_with(RIFF_START) && &data[8..12] == WEBP_MARKER {
+            Self::Webp
+        } else if serde_json::from_slice::<serde_json::Value>(data).is_ok() {
+            Self::Json
+        } else if is_printable_text(data) {
+            Self::Text
+        } else {
+            Self::Binary
+        }
+    }
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+            Self::Webp => "webp",
+            Self::Json => "json",
+            Self::Text => "txt",
+            Self::Binary => "bin",
+        }
+    }
+}
+
+impl fmt::Display for PayloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Png => "PNG image",
+            Self::Jpeg => "JPEG image",
+            Self::Gif => "GIF image",
+            Self::Webp => "WebP image",
+            Self::Json => "JSON",
+            Self::Text => "text",
+            Self::Binary => "binary data",
+        };
+        write!(f, "{name}")
+    }
+}
+
+pub struct DecodedPayload {
+    pub data: Vec<u8>,
+    /// The encodings that were removed, outermost first
+    pub encodings: Vec<&'static str>,
+    pub kind: PayloadKind,
+}
+
+/// Repeatedly strips base64 and gzip layers from a payload and detects what is left.
+///
+/// Base64 is only unwrapped when the decoded bytes are themselves recognizable (gzip, a known
+/// file format, or text), so plain text that happens to be valid base64 is left alone.
+#[must_use]
+pub fn decode_payload(payload: &[u8]) -> DecodedPayload {
+    let mut data = payload.to_vec();
+    let mut encodings = Vec::new();
+
+    while encodings.len() < MAX_DECODE_STEPS {
+        if data.starts_with(&GZIP_MAGIC) {
+            if let Ok(decompressed) = decompress_gzip_to_bytes(&Bytes::new(data.clone())) {
+                data = decompressed.to_vec();
+                encodings.push("gzip");
+                continue;
+            }
+        }
+
+        if let Some(decoded) = decode_base64_text(&data) {
+            if decoded.starts_with(&GZIP_MAGIC)
+                || PayloadKind::detect(&decoded) != PayloadKind::Binary
+            {
+                data = decoded;
+                encodings.push("base64");
+                continue;
+            }
+        }
+
+        break;
+    }
+
+    let kind = PayloadKind::detect(&data);
+    DecodedPayload {
+        data,
+        encodings,
+        kind,
+    }
+}
+
+fn is_printable_text(data: &[u8]) -> bool {
+    from_utf8(data).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || c.is_ascii_whitespace())
+    })
+}
+
+fn decode_base64_text(data: &[u8]) -> Option<Vec<u8>> {
+    let text: String = from_utf8(data)
+        .ok()?
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    if text.is_empty() {
+        return None;
+    }
+
+    general_purpose::STANDARD
+        .decode(&text)
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(&text))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn base64(data: &[u8]) -> Vec<u8> {
+        general_purpose::STANDARD.encode(data).into_bytes()
+    }
+
+    #[test]
+    fn nested_base64_and_gzip() {
+        let json = br#"{"name": "Chia God"}"#;
+        let decoded = decode_payload(&base64(&gzip(json)));
+        assert_eq!(decoded.encodings, ["base64", "gzip"]);
+        assert_eq!(decoded.kind, PayloadKind::Json);
+        assert_eq!(decoded.data, json);
+
+        let decoded = decode_payload(&base64(&gzip(&base64(json))));
+        assert_eq!(decoded.encodings, ["base64", "gzip", "base64"]);
+        assert_eq!(decoded.data, json);
+    }
+
+    #[test]
+    fn base64_png() {
+        let png = [&PNG_START[..], b"rest of the image"].concat();
+        let decoded = decode_payload(&base64(&png));
+        assert_eq!(decoded.encodings, ["base64"]);
+        assert_eq!(decoded.kind, PayloadKind::Png);
+        assert_eq!(decoded.data, png);
+    }
+
+    #[test]
+    fn non_utf8_is_left_alone() {
+        let data = [0xff, 0xfe, 0x00, 0x80, 0x41];
+        let decoded = decode_payload(&data);
+        assert!(decoded.encodings.is_empty());
+        assert_eq!(decoded.kind, PayloadKind::Binary);
+        assert_eq!(decoded.data, data);
+    }
+
+    #[test]
+    fn plain_text_is_unchanged() {
+        // The second and third are valid base64, but decode to binary
+        for text in ["Hello, world!", "Test", "abcd1234", "gods of chia"] {
+            let decoded = decode_payload(text.as_bytes());
+            assert!(decoded.encodings.is_empty(), "{text}");
+            assert_eq!(decoded.kind, PayloadKind::Text, "{text}");
+            assert_eq!(decoded.data, text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn gzip_that_cant_be_decompressed_is_left_alone() {
+        let data = [0x1f, 0x8b, 0x00, 0x00];
+        let decoded = decode_payload(&data);
+        assert!(decoded.encodings.is_empty());
+        assert_eq!(decoded.data, data);
+    }
+}
@@ -0,0 +1,215 @@
+//! Perceptual hashes, for matching images that were re-encoded, recompressed or resized.
+//!
+//! Three hashes are computed from a grayscale copy of the image: the 64 bit average hash
+//! (`aHash`) and difference hash (`dHash`), and the 63 bit DCT based perceptual hash (`pHash`).
+//! Similar images have hashes that differ in only a few bits.
+
+use anyhow::Result;
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma};
+use serde::Serialize;
+use std::f64::consts::PI;
+
+const DCT_SIZE: u32 = 32;
+const HASH_SIZE: u32 = 8;
+
+/// The perceptual hashes of an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ImageHashes {
+    pub ahash: u64,
+    pub dhash: u64,
+    pub phash: u64,
+}
+
+/// The number of bits that differ between the hashes of two images
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct HashDistance {
+    pub ahash: u32,
+    pub dhash: u32,
+    pub phash: u32,
+}
+
+impl ImageHashes {
+    /// Decodes an image in any supported format (PNG, JPEG, WebP or GIF) and hashes it
+    ///
+    /// # Errors
+    ///
+    /// Fails if the image can't be decoded.
+    pub fn of(data: &[u8]) -> Result<Self> {
+        let gray = flatten(&image::load_from_memory(data)?.to_rgba8());
+        Ok(Self {
+            ahash: average_hash(&gray),
+            dhash: difference_hash(&gray),
+            phash: perceptual_hash(&gray),
+        })
+    }
+
+    #[must_use]
+    pub fn distance(&self, other: &ImageHashes) -> HashDistance {
+        HashDistance {
+            ahash: (self.ahash ^ other.ahash).count_ones(),
+            dhash: (self.dhash ^ other.dhash).count_ones(),
+            phash: (self.phash ^ other.phash).count_ones(),
+        }
+    }
+}
+
+impl HashDistance {
+    /// The sum of the three distances, for ranking candidate matches
+    #[must_use]
+    pub fn total(&self) -> u32 {
+        self.ahash + self.dhash + self.phash
+    }
+
+    /// Whether every hash differs by at most `threshold` bits
+    #[must_use]
+    pub fn within(&self, threshold: u32) -> bool {
+        self.ahash.max(self.dhash).max(self.phash) <= threshold
+    }
+}
+
+/// Converts to grayscale, blending any transparency onto white, as marketplaces usually do when
+/// they re-encode to a format without an alpha channel
+fn flatten(image: &image::RgbaImage) -> GrayImage {
+    GrayImage::from_fn(image.width(), image.height(), |x, y| {
+        let [red, green, blue, alpha] = image.get_pixel(x, y).0;
+        let luma = 0.299 * f64::from(red) + 0.587 * f64::from(green) + 0.114 * f64::from(blue);
+        let alpha = f64::from(alpha) / 255.0;
+        Luma([(luma * alpha + 255.0 * (1.0 - alpha)).round() as u8])
+    })
+}
+
+/// Each bit is set when a pixel of the 8x8 image is brighter than the mean
+fn average_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, HASH_SIZE, HASH_SIZE, FilterType::Triangle);
+    let pixels: Vec<f64> = small.pixels().map(|pixel| f64::from(pixel.0[0])).collect();
+    let mean = pixels.iter().sum::<f64>() / pixels.len() as f64;
+    to_bits(pixels.iter().map(|pixel| *pixel > mean))
+}
+
+/// Each bit is set when a pixel of the 9x8 image is brighter than its right neighbour
+fn difference_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, HASH_SIZE + 1, HASH_SIZE, FilterType::Triangle);
+    to_bits((0..HASH_SIZE).flat_map(|y| {
+        let small = &small;
+        (0..HASH_SIZE).map(move |x| small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0])
+    }))
+}
+
+/// Each bit is set when one of the 8x8 lowest frequencies of the DCT of the 32x32 image is above
+/// their median. The DC term is left out: it is only the mean brightness, and it is so much larger
+/// than the rest that it would always set its bit and skew the median
+fn perceptual_hash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, DCT_SIZE, DCT_SIZE, FilterType::Triangle);
+    let size = DCT_SIZE as usize;
+    let hash_size = HASH_SIZE as usize;
+    let pixel = |x: usize, y: usize| f64::from(small.get_pixel(x as u32, y as u32).0[0]);
+    let basis = |frequency: usize, position: usize| {
+        ((2 * position + 1) as f64 * frequency as f64 * PI / (2 * size) as f64).cos()
+    };
+
+    // The DCT is separable, so transform the rows, then the columns of the low frequencies
+    let rows: Vec<Vec<f64>> = (0..size)
+        .map(|y| {
+            (0..hash_size)
+                .map(|u| (0..size).map(|x| pixel(x, y) * basis(u, x)).sum())
+                .collect()
+        })
+        .collect();
+    let coefficients: Vec<f64> = (0..hash_size)
+        .flat_map(|v| {
+            let rows = &rows;
+            (0..hash_size).map(move |u| (0..size).map(|y| rows[y][u] * basis(v, y)).sum())
+        })
+        .skip(1)
+        .collect();
+
+    let mut sorted = coefficients.clone();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+    to_bits(coefficients.iter().map(|coefficient| *coefficient > median))
+}
+
+fn to_bits(bits: impl Iterator<Item = bool>) -> u64 {
+    bits.fold(0, |hash, bit| (hash << 1) | u64::from(bit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    /// The default threshold of `match-images`
+    const THRESHOLD: u32 = 10;
+
+    /// A picture with shapes at several scales, drawn by `shade`
+    fn picture(shade: impl Fn(f64, f64) -> f64) -> DynamicImage {
+        let size = 256;
+        DynamicImage::ImageRgb8(RgbImage::from_fn(size, size, |x, y| {
+            let (x, y) = (
+                f64::from(x) / f64::from(size),
+                f64::from(y) / f64::from(size),
+            );
+            let value = (shade(x, y).clamp(0.0, 1.0) * 255.0) as u8;
+            image::Rgb([value, value / 2, 255 - value])
+        }))
+    }
+
+    fn original() -> DynamicImage {
+        picture(|x, y| {
+            let circle = f64::from(u8::from((x - 0.3).hypot(y - 0.4) < 0.2));
+            0.5 * circle + 0.3 * x + 0.2 * (y * 7.0).sin().abs()
+        })
+    }
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Cursor::new(vec![]);
+        image.write_to(&mut data, format).unwrap();
+        data.into_inner()
+    }
+
+    fn hashes(image: &DynamicImage) -> ImageHashes {
+        ImageHashes::of(&encode(image, ImageFormat::Png)).unwrap()
+    }
+
+    #[test]
+    fn recompressed_copy_matches() {
+        let original = original();
+        let mut jpeg = vec![];
+        original
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 60))
+            .unwrap();
+        let distance = hashes(&original).distance(&ImageHashes::of(&jpeg).unwrap());
+        assert!(distance.within(THRESHOLD), "{distance:?}");
+    }
+
+    #[test]
+    fn resized_copy_matches() {
+        let original = original();
+        let resized = original.resize(100, 100, FilterType::Lanczos3);
+        let distance = hashes(&original).distance(&hashes(&resized));
+        assert!(distance.within(THRESHOLD), "{distance:?}");
+    }
+
+    #[test]
+    fn different_image_does_not_match() {
+        let other = picture(|x, y| {
+            let square = f64::from(u8::from((0.5..0.9).contains(&x) && (0.1..0.6).contains(&y)));
+            0.6 * square + 0.4 * (1.0 - y) * (x * 5.0).cos().abs()
+        });
+        let distance = hashes(&original()).distance(&hashes(&other));
+        assert!(!distance.within(THRESHOLD), "{distance:?}");
+        assert!(distance.phash > THRESHOLD, "{distance:?}");
+    }
+
+    #[test]
+    fn phash_leaves_out_the_dc_term() {
+        // Brightening the whole image only changes the DC term
+        let original = original();
+        let brighter = DynamicImage::ImageRgb8(original.brighten(20).to_rgb8());
+        assert!(hashes(&original).phash < 1 << 63);
+        assert!(hashes(&original).distance(&hashes(&brighter)).phash <= 2);
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{sha256, PNG_START};
+
+pub struct Chunk<'a> {
+    pub chunk_type: [u8; 4],
+    pub data: &'a [u8],
+}
+
+impl Chunk<'_> {
+    /// Ancillary chunks have a lowercase first letter (e.g. `tEXt`, `pHYs`). A decoder may skip
+    /// them, though some, like `tRNS`, do change the decoded pixels
+    #[must_use]
+    pub fn is_ancillary(&self) -> bool {
+        self.chunk_type[0] & 0x20 != 0
+    }
+}
+
+/// Splits a PNG file into its chunks. Returns `None` if the data is not a complete PNG.
+#[must_use]
+pub fn chunks(data: &[u8]) -> Option<Vec<Chunk<'_>>> {
+    let mut rest = data.strip_prefix(&PNG_START[..])?;
+    let mut chunks = vec![];
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        let chunk_type: [u8; 4] = rest.get(4..8)?.try_into().ok()?;
+        let chunk_data = rest.get(8..8 + length)?;
+        // Skip the CRC as well
+        rest = rest.get(8 + length + 4..)?;
+        chunks.push(Chunk {
+            chunk_type,
+            data: chunk_data,
+        });
+    }
+    Some(chunks)
+}
+
+/// SHA-256 over the critical chunks and `tRNS` of a PNG, ignoring the other ancillary chunks and
+/// how the image data is split across `IDAT` chunks. Two PNGs with the same digest decode to the
+/// same pixels, though color space chunks like `gAMA` and `iCCP` are ignored, so they may still
+/// display differently. Returns `None` if the data is not a complete PNG.
+#[must_use]
+pub fn critical_digest(data: &[u8]) -> Option<[u8; 32]> {
+    let mut canonical = vec![];
+    let mut image_data = vec![];
+    for chunk in chunks(data)? {
+        if chunk.is_ancillary() && &chunk.chunk_type != b"tRNS" {
+            continue;
+        }
+        if &chunk.chunk_type == b"IDAT" {
+            image_data.extend_from_slice(chunk.data);
+        } else {
+            canonical.extend_from_slice(&chunk.chunk_type);
+            canonical.extend_from_slice(chunk.data);
+        }
+    }
+    canonical.extend_from_slice(b"IDAT");
+    canonical.extend_from_slice(&image_data);
+    Some(sha256(&canonical))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn encode_png() -> Vec<u8> {
+        let image = RgbImage::from_fn(4, 4, |x, y| image::Rgb([x as u8 * 60, y as u8 * 60, 128]));
+        let mut png = Cursor::new(vec![]);
+        image.write_to(&mut png, ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in bytes {
+            crc ^= u32::from(*byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    fn encode_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+        chunk
+    }
+
+    /// Inserts chunks just after `IHDR`, which is always first and 13 bytes long
+    fn insert_after_header(png: &[u8], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let at = PNG_START.len() + 8 + 13 + 4;
+        let mut result = png[..at].to_vec();
+        for chunk in chunks {
+            result.extend_from_slice(chunk);
+        }
+        result.extend_from_slice(&png[at..]);
+        result
+    }
+
+    /// Re-encodes the chunks, splitting `IDAT` into two
+    fn split_image_data(png: &[u8]) -> Vec<u8> {
+        let mut result = PNG_START.to_vec();
+        for chunk in chunks(png).unwrap() {
+            if &chunk.chunk_type == b"IDAT" {
+                let (first, second) = chunk.data.split_at(chunk.data.len() / 2);
+                result.extend(encode_chunk(b"IDAT", first));
+                result.extend(encode_chunk(b"IDAT", second));
+            } else {
+                result.extend(encode_chunk(&chunk.chunk_type, chunk.data));
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn splits_chunks() {
+        let png = encode_png();
+        let types: Vec<_> = chunks(&png)
+            .unwrap()
+            .iter()
+            .map(|chunk| chunk.chunk_type)
+            .collect();
+        assert_eq!(types.first(), Some(b"IHDR"));
+        assert_eq!(types.last(), Some(b"IEND"));
+        assert!(types.contains(b"IDAT"));
+
+        assert!(chunks(&png[..png.len() - 1]).is_none());
+        assert!(chunks(&png[1..]).is_none());
+    }
+
+    #[test]
+    fn ignores_ancillary_chunks() {
+        let png = encode_png();
+        let annotated = insert_after_header(
+            &png,
+            &[
+                encode_chunk(b"tEXt", b"Comment\0recovered"),
+                encode_chunk(b"pHYs", &[0, 0, 11, 19, 0, 0, 11, 19, 1]),
+                encode_chunk(b"gAMA", &45455u32.to_be_bytes()),
+            ],
+        );
+        let split = split_image_data(&annotated);
+
+        // Both are real PNGs with the same pixels
+        let pixels = image::load_from_memory(&png).unwrap().to_rgb8();
+        for other in [&annotated, &split] {
+            assert_ne!(&png, other);
+            assert_eq!(image::load_from_memory(other).unwrap().to_rgb8(), pixels);
+            assert_eq!(critical_digest(other), critical_digest(&png));
+        }
+    }
+
+    #[test]
+    fn transparency_changes_the_digest() {
+        let png = encode_png();
+        // Makes one of the colors transparent
+        let transparent =
+            insert_after_header(&png, &[encode_chunk(b"tRNS", &[0, 0, 0, 0, 0, 128])]);
+        assert_ne!(
+            image::load_from_memory(&transparent).unwrap().to_rgba8(),
+            image::load_from_memory(&png).unwrap().to_rgba8()
+        );
+        assert_ne!(critical_digest(&transparent), critical_digest(&png));
+    }
+
+    #[test]
+    fn image_data_changes_the_digest() {
+        let png = encode_png();
+        let mut other = Cursor::new(vec![]);
+        RgbImage::new(4, 4)
+            .write_to(&mut other, ImageFormat::Png)
+            .unwrap();
+        assert_ne!(critical_digest(&other.into_inner()), critical_digest(&png));
+        assert!(critical_digest(b"not a png").is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Thumbnails and contact sheets, for checking a recovered collection at a glance.
+//!
+//! Everything runs on the CPU with pure Rust decoding. Images that fail to decode are drawn as a
+//! red tile with a cross, so corruption stands out on the sheet.
+
+use anyhow::Result;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const CAPTION: Rgba<u8> = Rgba([230, 230, 230, 255]);
+const BROKEN: Rgba<u8> = Rgba([160, 24, 24, 255]);
+const PADDING: u32 = 4;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// An image on a contact sheet
+pub struct SheetEntry {
+    pub caption: String,
+    /// `None` when the image couldn't be decoded
+    pub thumbnail: Option<RgbaImage>,
+}
+
+/// Decodes a PNG and scales it to fit within `size` by `size` pixels, keeping its aspect ratio
+///
+/// # Errors
+///
+/// Fails if the data isn't a PNG that can be decoded.
+pub fn thumbnail(data: &[u8], size: u32) -> Result<RgbaImage> {
+    let image = image::load_from_memory_with_format(data, ImageFormat::Png)?;
+    Ok(image.resize(size, size, FilterType::Triangle).to_rgba8())
+}
+
+/// Lays thumbnails out in a grid of `columns`, each in a `size` by `size` cell with its caption
+/// underneath
+#[must_use]
+pub fn contact_sheet(entries: &[SheetEntry], size: u32, columns: u32) -> RgbaImage {
+    let columns = columns.max(1);
+    let rows = u32::try_from(entries.len())
+        .unwrap_or(u32::MAX)
+        .div_ceil(columns)
+        .max(1);
+    let cell_width = size + PADDING * 2;
+    let cell_height = size + GLYPH_HEIGHT + PADDING * 3;
+    let mut sheet = RgbaImage::from_pixel(columns * cell_width, rows * cell_height, BACKGROUND);
+
+    for (position, entry) in (0..).zip(entries) {
+        let left = (position % columns) * cell_width + PADDING;
+        let top = (position / columns) * cell_height + PADDING;
+        match &entry.thumbnail {
+            Some(thumbnail) => {
+                let x = left + (size - thumbnail.width()) / 2;
+                let y = top + (size - thumbnail.height()) / 2;
+                imageops::overlay(&mut sheet, thumbnail, i64::from(x), i64::from(y));
+            }
+            None => draw_broken(&mut sheet, left, top, size),
+        }
+
+        let max_chars = (size / (GLYPH_WIDTH + 1)) as usize;
+        let caption: String = entry.caption.chars().take(max_chars).collect();
+        draw_text(&mut sheet, &caption, left, top + size + PADDING);
+    }
+    sheet
+}
+
+fn draw_broken(sheet: &mut RgbaImage, left: u32, top: u32, size: u32) {
+    for offset in 0..size {
+        for y in 0..size {
+            sheet.put_pixel(left + offset, top + y, BROKEN);
+        }
+        sheet.put_pixel(left + offset, top + offset, CAPTION);
+        sheet.put_pixel(left + offset, top + size - 1 - offset, CAPTION);
+    }
+}
+
+fn draw_text(sheet: &mut RgbaImage, text: &str, left: u32, top: u32) {
+    for (index, character) in (0..).zip(text.chars()) {
+        let x = left + index * (GLYPH_WIDTH + 1);
+        for (y, row) in (0..).zip(glyph(character)) {
+            for column in 0..GLYPH_WIDTH {
+                if row & (0b10000 >> column) != 0 {
+                    sheet.put_pixel(x + column, top + y, CAPTION);
+                }
+            }
+        }
+    }
+}
+
+/// A 5x7 bitmap of a character, one byte per row with the leftmost pixel in the fifth bit.
+/// Letters are drawn in uppercase, and characters without a glyph as `?`
+fn glyph(character: char) -> [u8; 7] {
+    match character.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        ' ' => [0x00; 7],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        '#' => [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}